@@ -14,10 +14,36 @@ type ffiSolana interface {
 	/// Get current solana Instruction
 	get_ix() Ix
 
+	/// Get current solana Instruction as a Validator, nil if it doesn't have a Validate method
+	get_validator() Validator
+
+	/// Decode the current instruction, false if it fails the instruction
+	prepare_ix() bool
+
 	get_id() *PublicKey
 
 	account_create(payer, account Account, space uint64, signerSeeds []SeedBump) unsafe.Pointer
 
+	account_create_with_owner(payer, account Account, space uint64, owner *PublicKey, signerSeeds []SeedBump) unsafe.Pointer
+
+	account_create_with_seed(payer, account, base Account, seed string, space uint64, owner *PublicKey, signerSeeds []SeedBump) unsafe.Pointer
+
+	system_transfer(from, to Account, lamports uint64, signerSeeds []SeedBump) unsafe.Pointer
+
+	transfer_lamports(from, to Account, amount uint64) unsafe.Pointer
+
+	account_allocate(account Account, space uint64, signerSeeds []SeedBump) unsafe.Pointer
+
+	account_assign(account Account, owner *PublicKey, signerSeeds []SeedBump) unsafe.Pointer
+
+	invoke(program Program, metas []AccountMeta, data []byte, signerSeeds []SeedBump) unsafe.Pointer
+
+	invoke_golana(loader Program, memDump Account, id string, metas []AccountMeta, args []byte, signerSeeds []SeedBump) unsafe.Pointer
+
+	anchor_sighash(name string) [8]uint8
+
+	serialize(data interface{}) ([]byte, unsafe.Pointer)
+
 	account_key(account Account) *PublicKey
 
 	account_lamports(account Account) uint64
@@ -32,11 +58,54 @@ type ffiSolana interface {
 
 	account_data(account Account) interface{}
 
-	account_save_data(account Account, data interface{})
+	account_save_data(account Account, data interface{}) unsafe.Pointer
+
+	account_data_as(account Account, typ interface{}) (interface{}, unsafe.Pointer)
+
+	account_read_field(account Account, path string) (interface{}, unsafe.Pointer)
+
+	account_write_field(account Account, path string, value interface{}) unsafe.Pointer
+
+	account_data_bytes(account Account) []byte
+
+	account_read_bytes(account Account, offset, length uint64) ([]byte, unsafe.Pointer)
+
+	account_write_bytes(account Account, offset uint64, data []byte) unsafe.Pointer
+
+	account_resize(account, payer Account, newSize uint64, signerSeeds []SeedBump) unsafe.Pointer
+
+	program_main() func()
+
+	commit_data() bool
+
+	old_data_accounts() []Account
+
+	account_old_data(account Account) Migrator
+
+	account_set_migrated_data(account Account, data interface{}) unsafe.Pointer
+
+	space_of(data interface{}) uint64
 
 	error_string(ptr unsafe.Pointer) string
 
 	log_compute_unit()
 
-	find_program_address(seed string, program *PublicKey) (*PublicKey, uint8)
+	// The bump comes first, the VM loses a number returned in the second place by an FFI
+	find_program_address(seed string, program *PublicKey) (uint8, *PublicKey)
+
+	create_program_address(seed string, bump uint8, program *PublicKey) (*PublicKey, unsafe.Pointer)
+
+	verify_pda(account Account, seed string, bump uint8) unsafe.Pointer
+
+	clock() *ClockSysvar
+
+	rent() *RentSysvar
+
+	epoch_schedule() *EpochScheduleSysvar
+
+	load_current_index(sysvar Account) (uint16, unsafe.Pointer)
+
+	load_instruction_at(sysvar Account, index uint16) (*TxInstruction, unsafe.Pointer)
+
+	get_instruction_relative(sysvar Account, offset int64) (*TxInstruction, unsafe.Pointer)
 }
@@ -15,12 +15,138 @@ type SeedBump struct {
 	Bump uint8
 }
 
+// Builds a seed out of multiple parts, so that a PDA can be derived from e.g. a name and
+// some keys: Seeds("position", owner.Key(), pool.Key()). The parts can be strings, []byte,
+// *PublicKey, and integers, which are encoded in little-endian.
+// Every part is prefixed with its length as a little-endian uint32, so that different
+// parts never make the same seed, e.g. Seeds("ab", "c") and Seeds("a", "bc").
+func Seeds(parts ...interface{}) string {
+	s := ""
+	for _, part := range parts {
+		var b string
+		switch v := part.(type) {
+		case string:
+			b = v
+		case []byte:
+			b = string(v)
+		// The VM doesn't tell PublicKey from *PublicKey in a type switch, so only the
+		// pointer is accepted, as returned by Key() and GetId()
+		case *PublicKey:
+			b = keyBytes(v)
+		case uint8:
+			b = leBytes(uint64(v), 1)
+		case uint16:
+			b = leBytes(uint64(v), 2)
+		case uint32:
+			b = leBytes(uint64(v), 4)
+		case uint64:
+			b = leBytes(v, 8)
+		case uint:
+			b = leBytes(uint64(v), 8)
+		case int8:
+			b = leBytes(uint64(v), 1)
+		case int16:
+			b = leBytes(uint64(v), 2)
+		case int32:
+			b = leBytes(uint64(v), 4)
+		case int64:
+			b = leBytes(uint64(v), 8)
+		case int:
+			b = leBytes(uint64(v), 8)
+		default:
+			panic("Seeds: unsupported seed type")
+		}
+		s += leBytes(uint64(len(b)), 4) + b
+	}
+	return s
+}
+
+func keyBytes(pk *PublicKey) string {
+	b := make([]byte, len(pk))
+	for i := 0; i < len(b); i++ {
+		b[i] = byte(pk[i])
+	}
+	return string(b)
+}
+
+func leBytes(v uint64, n int) string {
+	b := make([]byte, n)
+	for i := 0; i < n; i++ {
+		b[i] = byte(v >> (uint(i) * 8))
+	}
+	return string(b)
+}
+
 // The Solana Account, represented by a index in the Golana runtime
 type Account uint
 
 // The Solana Program is a kind of Account
 type Program Account
 
+// Describes how an account is passed to a cross-program invocation, see Invoke
+type AccountMeta struct {
+	Account    Account
+	IsSigner   bool
+	IsWritable bool
+}
+
+// Invokes an instruction of any on-chain program (CPI)
+// program: the program to call, it has to be listed in the Ix as well
+// metas: the accounts required by the instruction, in the order the program expects
+// data: the instruction data, encoded the way the program expects
+// signerSeeds: used when the singers are PDAs, pass nil if no signer is PDA
+// The data returned by Data() is written to the mutable accounts before the call, and
+// reloaded in place afterwards for the accounts passed to it, same for all the other CPIs.
+func Invoke(program Program, metas []AccountMeta, data []byte, signerSeeds []SeedBump) error {
+	p := solFfi.invoke(program, metas, data, signerSeeds)
+	return NewSolanaError(p)
+}
+
+// Invokes an instruction of another Golana program (CPI), both programs run on the same loader
+// loader: the Golana loader program, it has to be listed in the Ix
+// memDump: the memory dump account of the program to call, which identifies the program
+// ix: the name of the instruction, e.g. "IxDeposit"
+// metas: the accounts of the instruction, in the order they are declared in the Ix
+// args: a struct with the args of the instruction in order, nil if there is none
+// signerSeeds: used when the singers are PDAs of this program, pass nil if no signer is PDA
+func InvokeGolana(loader Program, memDump Account, ix string, metas []AccountMeta, args interface{}, signerSeeds []SeedBump) error {
+	var data []byte
+	if args != nil {
+		b, err := Serialize(args)
+		if err != nil {
+			return err
+		}
+		data = b
+	}
+	p := solFfi.invoke_golana(loader, memDump, ix, metas, data, signerSeeds)
+	return NewSolanaError(p)
+}
+
+// Serializes the data in borsh format, the same way the args and account data are encoded.
+// data can be a struct or a pointer to a struct, this is mostly used to build instruction data
+func Serialize(data interface{}) ([]byte, error) {
+	b, p := solFfi.serialize(data)
+	return b, NewSolanaError(p)
+}
+
+// Returns the 8-byte discriminator Anchor puts in front of the instruction data,
+// i.e. sha256("global:<name>")[..8], where name is the snake_case name of the
+// method in the Anchor program, e.g. "create_pool"
+func AnchorSighash(name string) [8]uint8 {
+	return solFfi.anchor_sighash(name)
+}
+
+// Builds the data of an Anchor instruction: the sighash of the method followed by the args,
+// args is a struct with the args of the method in order, see Serialize
+func AnchorIxData(name string, args interface{}) ([]byte, error) {
+	b, err := Serialize(args)
+	if err != nil {
+		return nil, err
+	}
+	sighash := AnchorSighash(name)
+	return append(sighash[:], b...), nil
+}
+
 // Initializes a new Account by calling the solana runtime createAccount function
 // payer (account:"mut, signer"): pays for the lamports
 // space: the space required for the account
@@ -42,19 +168,104 @@ func (account Account) Lamports() uint64 {
 	return solFfi.account_lamports(account)
 }
 
+// Moves lamports between accounts without a CPI, which is the only way to take lamports
+// from an account owned by the Golana loader, i.e. created with Create.
+// Returns an error if from is not owned by the loader, any account is not mutable,
+// from doesn't have enough lamports, or would no longer be rent exempt.
+// Taking all the lamports of from is allowed, to close it.
+// Note that the loader owns the accounts of all Golana programs, not only of this one,
+// so the program has to check that from is one of its own, e.g. with VerifyPda.
+func TransferLamports(from, to Account, amount uint64) error {
+	p := solFfi.transfer_lamports(from, to, amount)
+	return NewSolanaError(p)
+}
+
 // Set the lamports in the account
 func (account Account) SetLamports(lamports uint64) {
 	solFfi.account_set_lamports(account, lamports)
 }
 
 // Get the data of the account
+// account: has to be owned by the Golana loader
 func (account Account) Data() interface{} {
 	return solFfi.account_data(account)
 }
 
+// Get the data of the account as the type of typ, which is a pointer to a struct
+// declared at package level, e.g. acc.DataAs(&PoolData{}). The result has the same type
+// as typ, and like with the data: tag, the account must start with the discriminator of
+// the type. Unlike Data(), the result is not saved at the end of the instruction.
+// account: has to be owned by the Golana loader, use DataBytes for the accounts of other programs
+func (account Account) DataAs(typ interface{}) (interface{}, error) {
+	data, p := solFfi.account_data_as(account, typ)
+	return data, NewSolanaError(p)
+}
+
+// Read a single field of the data of an account declared with `data:"T,zerocopy"`,
+// without deserializing the rest. The path is like "bids[3].price", and the result has
+// the type of the field, e.g. book.ReadField("count").(uint32)
+// account: has to be owned by the Golana loader
+func (account Account) ReadField(path string) (interface{}, error) {
+	v, p := solFfi.account_read_field(account, path)
+	return v, NewSolanaError(p)
+}
+
+// Write a single field of the data of an account declared with `data:"T,zerocopy"`,
+// the value has to be of the exact type of the field, e.g. uint32(3) rather than 3.
+// Like with WriteBytes, any data loaded with Data() is saved first, and then updated in place.
+// account (account:"mut"): has to be owned by the Golana loader
+func (account Account) WriteField(path string, value interface{}) error {
+	p := solFfi.account_write_field(account, path, value)
+	return NewSolanaError(p)
+}
+
+// Get a copy of the raw data of the account, e.g. to parse an account of another program
+// with a custom layout
+func (account Account) DataBytes() []byte {
+	return solFfi.account_data_bytes(account)
+}
+
+// Get a copy of length bytes of the raw data of the account, starting at offset
+// Returns an error if the range is out of the data
+func (account Account) ReadBytes(offset, length uint64) ([]byte, error) {
+	b, p := solFfi.account_read_bytes(account, offset, length)
+	return b, NewSolanaError(p)
+}
+
+// Overwrite the raw data of the account at offset with data. Any data loaded with Data()
+// is saved first, and then updated in place with the new bytes.
+// account (account:"mut"): has to be owned by the Golana loader, which owns the accounts of
+// all Golana programs, so make sure it's one of this program's
+// Returns an error if the range is out of the data, see Resize
+func (account Account) WriteBytes(offset uint64, data []byte) error {
+	p := solFfi.account_write_bytes(account, offset, data)
+	return NewSolanaError(p)
+}
+
 // Save the data of the account
-func (account Account) SaveData(data interface{}) {
-	solFfi.account_save_data(account, data)
+// Returns an error if the account is not big enough for the data, see Resize
+func (account Account) SaveData(data interface{}) error {
+	p := solFfi.account_save_data(account, data)
+	return NewSolanaError(p)
+}
+
+// Resize the account's data, and transfer the lamports required for it to be rent exempt
+// account (account:"mut"): has to be owned by the loader
+// payer (account:"mut, signer"): pays for the extra lamports if the account grows, and gets
+// back the lamports above the rent exempt minimum if it shrinks
+// newSize: the new size of the account's data, it can grow by at most 10KB per instruction
+// signerSeeds: used when the payer is a PDA, pass nil otherwise
+// Required Program(s):
+//   - SystemProgram
+func (account Account) Resize(newSize uint64, payer Account, signerSeeds []SeedBump) error {
+	p := solFfi.account_resize(account, payer, newSize, signerSeeds)
+	return NewSolanaError(p)
+}
+
+// Get the space required by an account to store the data, including the 8-byte discriminator.
+// data has to be of a type used in a `data:"..."` tag, e.g. SpaceOf(MyData{}) or SpaceOf(new(MyData))
+func SpaceOf(data interface{}) uint64 {
+	return solFfi.space_of(data)
 }
 
 // Get the program that owns this account
@@ -77,11 +288,57 @@ type Ix interface {
 	Process()
 }
 
+// Implemented by instructions that check their accounts and arguments before Process,
+// which is where access control is supposed to go
+type Validator interface {
+	Validate() error
+}
+
+// The old versions of a data type declared with `data:"T,version=N"` are kept in the
+// program as TV0, TV1, ..., and implement Migrator to convert to the current version:
+// func (old *TV1) Migrate() interface{} { return &T{...} }
+type Migrator interface {
+	Migrate() interface{}
+}
+
+// Converts the data of the accounts that still hold an old version of their data type,
+// the converted data is saved if the account is mut
+func migrateData() {
+	for _, account := range solFfi.old_data_accounts() {
+		data := solFfi.account_old_data(account).Migrate()
+		AbortOnError(NewSolanaError(solFfi.account_set_migrated_data(account, data)))
+	}
+}
+
 // Returns the current instruction
 func GetIx() Ix {
 	return solFfi.get_ix()
 }
 
+// Runs the current instruction, this is what main() is supposed to call.
+// It calls the Process method of the instruction, the Validate method, if there is one,
+// has already been called before main(). The data returned by Data() is written back to
+// the mutable accounts after main() returns, so there is no need to call SaveData for them.
+func Execute() {
+	GetIx().Process()
+}
+
+// The loader runs this in the place of main(). It decodes the instruction, migrates the
+// account data of old versions, see Migrator, calls the Validate method of the instruction
+// if there is one, aborting if it returns an error, then main(), and then writes the data
+// returned by Data() back to the mutable accounts
+func runMain() {
+	if !solFfi.prepare_ix() {
+		return
+	}
+	migrateData()
+	if v := solFfi.get_validator(); v != nil {
+		AbortOnError(v.Validate())
+	}
+	solFfi.program_main()()
+	solFfi.commit_data()
+}
+
 // Returns the current program id, i.e. bytecode PubKey
 func GetId() *PublicKey {
 	return solFfi.get_id()
@@ -107,8 +364,24 @@ func LogComputeUnit() {
 }
 
 // Find a valid "program derived address"(PDA) and its corresponding bump seed.
+// Use Seeds to derive the PDA from multiple seeds, the same goes for SeedBump.Seed
 func FindProgramAddress(seed string, pk *PublicKey) (*PublicKey, uint8) {
-	return solFfi.find_program_address(seed, pk)
+	bump, addr := solFfi.find_program_address(seed, pk)
+	return addr, bump
+}
+
+// Returns the PDA of the seed and the bump, it's much cheaper than FindProgramAddress
+// when the bump is known, e.g. stored in the account data or passed in as an argument.
+// Returns an error if the seed and bump don't make a valid PDA
+func CreateProgramAddress(seed string, bump uint8, pk *PublicKey) (*PublicKey, error) {
+	addr, p := solFfi.create_program_address(seed, bump, pk)
+	return addr, NewSolanaError(p)
+}
+
+// Returns an error if the account is not the PDA of this program with the seed and bump
+func VerifyPda(account Account, seed string, bump uint8) error {
+	p := solFfi.verify_pda(account, seed, bump)
+	return NewSolanaError(p)
 }
 
 // For internal use only
@@ -0,0 +1,56 @@
+package solana
+
+// Like Create, but the new Account is owned by owner rather than the Golana loader,
+// so that it can be used by another program.
+// payer (account:"mut, signer"): pays for the lamports
+// signerSeeds: used when the singers are PDAs, pass nil if no signer is PDA
+// Required Program(s):
+//   - SystemProgram
+func (account Account) CreateWithOwner(payer Account, space uint64, owner *PublicKey, signerSeeds []SeedBump) error {
+	p := solFfi.account_create_with_owner(payer, account, space, owner, signerSeeds)
+	return NewSolanaError(p)
+}
+
+// Initializes a new Account at the address derived from base, seed and owner,
+// see createAccountWithSeed of the system program
+// payer (account:"mut, signer"): pays for the lamports
+// base (account:"signer"): the base of the address, can be the same as payer
+// signerSeeds: used when the singers are PDAs, pass nil if no signer is PDA
+// Required Program(s):
+//   - SystemProgram
+func (account Account) CreateWithSeed(payer, base Account, seed string, space uint64, owner *PublicKey, signerSeeds []SeedBump) error {
+	p := solFfi.account_create_with_seed(payer, account, base, seed, space, owner, signerSeeds)
+	return NewSolanaError(p)
+}
+
+// Transfers lamports with the system program, from must be owned by the system program,
+// e.g. a wallet or a PDA vault holding only SOL
+// from (account:"mut, signer"): pays the lamports
+// to (account:"mut"): receives the lamports
+// signerSeeds: used when the singers are PDAs, pass nil if no signer is PDA
+// Required Program(s):
+//   - SystemProgram
+func TransferSol(from, to Account, lamports uint64, signerSeeds []SeedBump) error {
+	p := solFfi.system_transfer(from, to, lamports, signerSeeds)
+	return NewSolanaError(p)
+}
+
+// Allocates space for an Account owned by the system program
+// account (account:"mut, signer")
+// signerSeeds: used when the singers are PDAs, pass nil if no signer is PDA
+// Required Program(s):
+//   - SystemProgram
+func (account Account) Allocate(space uint64, signerSeeds []SeedBump) error {
+	p := solFfi.account_allocate(account, space, signerSeeds)
+	return NewSolanaError(p)
+}
+
+// Assigns an Account owned by the system program to owner
+// account (account:"mut, signer")
+// signerSeeds: used when the singers are PDAs, pass nil if no signer is PDA
+// Required Program(s):
+//   - SystemProgram
+func (account Account) Assign(owner *PublicKey, signerSeeds []SeedBump) error {
+	p := solFfi.account_assign(account, owner, signerSeeds)
+	return NewSolanaError(p)
+}
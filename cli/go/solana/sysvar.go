@@ -0,0 +1,97 @@
+package solana
+
+// The Clock sysvar, see Clock()
+type ClockSysvar struct {
+	// The current slot
+	Slot uint64
+	// The timestamp of the first slot in this epoch
+	EpochStartTimestamp int64
+	// The current epoch
+	Epoch uint64
+	// The future epoch for which the leader schedule has most recently been calculated
+	LeaderScheduleEpoch uint64
+	// The approximate real world time of the current slot, in seconds since the Unix epoch
+	UnixTimestamp int64
+}
+
+// The Rent sysvar, see Rent()
+type RentSysvar struct {
+	LamportsPerByteYear uint64
+	// The number of years of rent an account must hold to be exempt from rent
+	ExemptionThreshold float64
+	BurnPercent        uint8
+}
+
+// The EpochSchedule sysvar, see EpochSchedule()
+type EpochScheduleSysvar struct {
+	SlotsPerEpoch            uint64
+	LeaderScheduleSlotOffset uint64
+	// Whether epochs start short and grow
+	Warmup           bool
+	FirstNormalEpoch uint64
+	FirstNormalSlot  uint64
+}
+
+// The bytes of an account's metadata counted for the rent, on top of its data
+const ACCOUNT_STORAGE_OVERHEAD = 128
+
+// Returns the Clock sysvar, with the current slot and unix timestamp
+func Clock() *ClockSysvar {
+	return solFfi.clock()
+}
+
+// Returns the Rent sysvar
+func Rent() *RentSysvar {
+	return solFfi.rent()
+}
+
+// Returns the EpochSchedule sysvar
+func EpochSchedule() *EpochScheduleSysvar {
+	return solFfi.epoch_schedule()
+}
+
+// The minimum balance for an account with dataLen bytes of data to be rent exempt
+func (r *RentSysvar) MinimumBalance(dataLen uint64) uint64 {
+	bytes := ACCOUNT_STORAGE_OVERHEAD + dataLen
+	return uint64(float64(bytes*r.LamportsPerByteYear) * r.ExemptionThreshold)
+}
+
+// Whether the balance is enough for an account with dataLen bytes of data to be rent exempt
+func (r *RentSysvar) IsExempt(balance uint64, dataLen uint64) bool {
+	return balance >= r.MinimumBalance(dataLen)
+}
+
+// An account of a TxInstruction
+type TxAccountMeta struct {
+	Key        PublicKey
+	IsSigner   bool
+	IsWritable bool
+}
+
+// An instruction of the current transaction, loaded from the Instructions sysvar.
+// Note that the instructions of Golana programs have the Golana loader as the ProgramId
+type TxInstruction struct {
+	ProgramId PublicKey
+	Accounts  []TxAccountMeta
+	Data      []byte
+}
+
+// Returns the index of the currently executing instruction in the transaction.
+// sysvar is the Instructions sysvar account, which needs to be listed in the Ix.
+func LoadCurrentIndex(sysvar Account) (uint16, error) {
+	index, p := solFfi.load_current_index(sysvar)
+	return index, NewSolanaError(p)
+}
+
+// Returns the instruction at the index in the transaction, see LoadCurrentIndex
+func LoadInstructionAt(sysvar Account, index uint16) (*TxInstruction, error) {
+	ix, p := solFfi.load_instruction_at(sysvar, index)
+	return ix, NewSolanaError(p)
+}
+
+// Returns the instruction at the offset relative to the currently executing one,
+// e.g. -1 for the previous instruction, see LoadCurrentIndex
+func GetInstructionRelative(sysvar Account, offset int64) (*TxInstruction, error) {
+	ix, p := solFfi.get_instruction_relative(sysvar, offset)
+	return ix, NewSolanaError(p)
+}
@@ -1,16 +1,36 @@
 use anchor_syn::idl;
 use anyhow::{anyhow, Result};
-use go_vm::types::{Meta, MetadataObjs, MetadataType, ValueType};
+use go_vm::types::{Meta, MetadataKey, MetadataObjs, MetadataType, VMObjects, ValueType};
 use golana;
+use std::cell::RefCell;
+use std::collections::HashMap;
 
 pub struct IdlGen<'a> {
     meta_objs: &'a MetadataObjs,
     tx_meta: &'a golana::TxMeta,
+    // Names of the types declared in the Go packages, used to name the `defined` types
+    type_names: HashMap<MetadataKey, String>,
+    // The user defined types referenced so far, they go to the `types` section of the IDL
+    defined_types: RefCell<Vec<idl::IdlTypeDefinition>>,
 }
 
 impl IdlGen<'_> {
-    pub fn new<'a>(meta_objs: &'a MetadataObjs, tx_meta: &'a golana::TxMeta) -> IdlGen<'a> {
-        IdlGen { meta_objs, tx_meta }
+    pub fn new<'a>(objs: &'a VMObjects, tx_meta: &'a golana::TxMeta) -> IdlGen<'a> {
+        let mut type_names = HashMap::new();
+        for pkg in objs.packages.iter() {
+            for (name, index) in pkg.member_indices() {
+                let member = pkg.member(*index);
+                if member.typ() == ValueType::Metadata {
+                    type_names.insert(member.as_metadata().key, name.clone());
+                }
+            }
+        }
+        IdlGen {
+            meta_objs: &objs.metas,
+            tx_meta,
+            type_names,
+            defined_types: RefCell::new(vec![]),
+        }
     }
 
     pub fn gen(&self, proj_name: &str) -> Result<idl::Idl> {
@@ -20,14 +40,15 @@ impl IdlGen<'_> {
             .iter()
             .map(|inst| self.get_ix_idl(inst))
             .collect::<Result<Vec<idl::IdlInstruction>>>()?;
+        let accounts = self.get_accounts_idl()?;
         Ok(idl::Idl {
             version: "0.0.0".to_owned(),
             name: proj_name.to_owned(),
             docs: None,
             instructions,
-            accounts: vec![],
+            accounts,
             constants: vec![],
-            types: vec![],
+            types: self.defined_types.take(),
             errors: None,
             events: None,
             metadata: None,
@@ -66,47 +87,213 @@ impl IdlGen<'_> {
             is_signer: acc.is_signer,
             is_mut: acc.is_mut,
             is_optional: None,
-            docs: None,
+            // Links the account to the type of its data, which is in the `accounts` section
+            docs: acc
+                .data_meta
+                .as_ref()
+                .map(|data_meta| vec![format!("data: {}", data_meta.name)]),
             pda: None,
             relations: vec![],
         }
     }
 
+    /// Every type used as account data, i.e. referenced by a `data:"..."` tag
+    fn get_accounts_idl(&self) -> Result<Vec<idl::IdlTypeDefinition>> {
+        let mut accounts: Vec<idl::IdlTypeDefinition> = vec![];
+        for data_meta in self
+            .tx_meta
+            .instructions
+            .iter()
+            .flat_map(|ix| ix.accounts.iter())
+            .filter_map(|acc| acc.data_meta.as_ref())
+        {
+            // The current version goes by the name of the type, the old ones are included as
+            // `T_v<k>` so that the accounts not yet migrated can be decoded too. The name the
+            // data is stored under, which decides the discriminator, is in the docs.
+            let versions = data_meta
+                .old_versions
+                .iter()
+                .map(|x| {
+                    (
+                        format!("{}_v{}", data_meta.name, x.version),
+                        golana::codec::versioned_name(&data_meta.name, x.version),
+                        &x.meta,
+                    )
+                })
+                .chain(std::iter::once((
+                    data_meta.name.clone(),
+                    data_meta.stored_name(),
+                    &data_meta.meta,
+                )));
+            for (name, stored_name, meta) in versions {
+                if accounts.iter().all(|x| x.name != name) {
+                    accounts.push(idl::IdlTypeDefinition {
+                        docs: (stored_name != name)
+                            .then(|| vec![format!("stored as: {}", stored_name)]),
+                        name,
+                        ty: self.get_type_def_ty(meta)?,
+                    });
+                }
+            }
+        }
+        Ok(accounts)
+    }
+
+    fn get_type_def_ty(&self, typ: &Meta) -> Result<idl::IdlTypeDefinitionTy> {
+        match &self.meta_objs[typ.key] {
+            MetadataType::Named(_, inner) => self.get_type_def_ty(inner),
+            MetadataType::Struct(fields) => Ok(idl::IdlTypeDefinitionTy::Struct {
+                fields: fields
+                    .infos()
+                    .iter()
+                    .map(|f| {
+                        Ok(idl::IdlField {
+                            name: f.name.clone(),
+                            docs: None,
+                            ty: self.get_idl_type(&f.meta)?,
+                        })
+                    })
+                    .collect::<Result<Vec<idl::IdlField>>>()?,
+            }),
+            _ => Err(anyhow!("Data type has to be a struct")),
+        }
+    }
+
     fn get_idl_type(&self, typ: &Meta) -> Result<idl::IdlType> {
-        match self.meta_objs[typ.key] {
-            MetadataType::Array(t, size) => Ok(idl::IdlType::Array(
-                Box::new(self.gos_type_to_idl_type(&t)?),
-                size,
-            )),
+        if typ.ptr_depth == 1 {
+            return Ok(idl::IdlType::Option(Box::new(
+                self.get_idl_type(&typ.unptr_to())?,
+            )));
+        }
+        match &self.meta_objs[typ.key] {
+            MetadataType::Array(t, size) => {
+                Ok(idl::IdlType::Array(Box::new(self.get_idl_type(t)?), *size))
+            }
             MetadataType::Slice(t) => {
                 if t.value_type(self.meta_objs) == ValueType::Uint8 {
                     Ok(idl::IdlType::Bytes)
                 } else {
-                    Ok(idl::IdlType::Vec(Box::new(self.gos_type_to_idl_type(&t)?)))
+                    Ok(idl::IdlType::Vec(Box::new(self.get_idl_type(t)?)))
                 }
             }
+            MetadataType::Map(k, v) => self.get_map_type(k, v),
             MetadataType::Named(_, inner) => {
                 if typ == &self.tx_meta.pub_key_meta {
                     Ok(idl::IdlType::PublicKey)
+                } else if let MetadataType::Struct(_) = &self.meta_objs[inner.key] {
+                    self.get_defined_type(typ, inner)
+                } else if let MetadataType::Interface(_) = &self.meta_objs[inner.key] {
+                    self.get_defined_enum(typ)
                 } else {
-                    self.get_idl_type(&inner)
+                    self.get_idl_type(inner)
                 }
             }
             _ => self.gos_type_to_idl_type(typ),
         }
     }
 
+    fn get_defined_type(&self, typ: &Meta, inner: &Meta) -> Result<idl::IdlType> {
+        let name = self
+            .type_names
+            .get(&typ.key)
+            .ok_or(anyhow!("Only package level types can be used in the IDL"))?;
+        let defined = self.defined_types.borrow().iter().any(|x| &x.name == name);
+        if !defined {
+            let ty = self.get_type_def_ty(inner)?;
+            self.defined_types
+                .borrow_mut()
+                .push(idl::IdlTypeDefinition {
+                    name: name.clone(),
+                    docs: None,
+                    ty,
+                });
+        }
+        Ok(idl::IdlType::Defined(name.clone()))
+    }
+
+    /// A map is represented as a vector of key/value pairs, the pair is a `defined` struct
+    /// named after the key and value types, e.g. `MapEntryPublicKeyU64`
+    fn get_map_type(&self, k: &Meta, v: &Meta) -> Result<idl::IdlType> {
+        let key = self.get_idl_type(k)?;
+        let value = self.get_idl_type(v)?;
+        let name = format!("MapEntry{}{}", type_name(&key), type_name(&value));
+        let defined = self.defined_types.borrow().iter().any(|x| x.name == name);
+        if !defined {
+            self.defined_types
+                .borrow_mut()
+                .push(idl::IdlTypeDefinition {
+                    name: name.clone(),
+                    docs: None,
+                    ty: idl::IdlTypeDefinitionTy::Struct {
+                        fields: vec![
+                            idl::IdlField {
+                                name: "key".to_owned(),
+                                docs: None,
+                                ty: key,
+                            },
+                            idl::IdlField {
+                                name: "value".to_owned(),
+                                docs: None,
+                                ty: value,
+                            },
+                        ],
+                    },
+                });
+        }
+        Ok(idl::IdlType::Vec(Box::new(idl::IdlType::Defined(name))))
+    }
+
+    fn get_defined_enum(&self, typ: &Meta) -> Result<idl::IdlType> {
+        let enum_meta = self
+            .tx_meta
+            .enums
+            .iter()
+            .find(|x| x.meta.key == typ.key)
+            .ok_or(anyhow!("Enum not found: {:?}", typ))?;
+        let defined = self
+            .defined_types
+            .borrow()
+            .iter()
+            .any(|x| x.name == enum_meta.name);
+        if !defined {
+            let variants = enum_meta
+                .variants
+                .iter()
+                .map(|(name, meta)| {
+                    let fields = match self.get_type_def_ty(meta)? {
+                        idl::IdlTypeDefinitionTy::Struct { fields } if !fields.is_empty() => {
+                            Some(idl::EnumFields::Named(fields))
+                        }
+                        _ => None,
+                    };
+                    Ok(idl::IdlEnumVariant {
+                        name: name.clone(),
+                        fields,
+                    })
+                })
+                .collect::<Result<Vec<idl::IdlEnumVariant>>>()?;
+            self.defined_types
+                .borrow_mut()
+                .push(idl::IdlTypeDefinition {
+                    name: enum_meta.name.clone(),
+                    docs: None,
+                    ty: idl::IdlTypeDefinitionTy::Enum { variants },
+                });
+        }
+        Ok(idl::IdlType::Defined(enum_meta.name.clone()))
+    }
+
     fn gos_type_to_idl_type(&self, typ: &Meta) -> Result<idl::IdlType> {
         let vt = typ.value_type(self.meta_objs);
         match vt {
             ValueType::Uint8 => Ok(idl::IdlType::U8),
             ValueType::Uint16 => Ok(idl::IdlType::U16),
             ValueType::Uint32 => Ok(idl::IdlType::U32),
-            ValueType::Uint64 => Ok(idl::IdlType::U64),
+            ValueType::Uint64 | ValueType::Uint => Ok(idl::IdlType::U64),
             ValueType::Int8 => Ok(idl::IdlType::I8),
             ValueType::Int16 => Ok(idl::IdlType::I16),
             ValueType::Int32 => Ok(idl::IdlType::I32),
-            ValueType::Int64 => Ok(idl::IdlType::I64),
+            ValueType::Int64 | ValueType::Int => Ok(idl::IdlType::I64),
             ValueType::Float32 => Ok(idl::IdlType::F32),
             ValueType::Float64 => Ok(idl::IdlType::F64),
             ValueType::Bool => Ok(idl::IdlType::Bool),
@@ -115,3 +302,15 @@ impl IdlGen<'_> {
         }
     }
 }
+
+/// A name for the type that can be used as part of an identifier
+fn type_name(ty: &idl::IdlType) -> String {
+    match ty {
+        idl::IdlType::Defined(name) => name.clone(),
+        idl::IdlType::Option(t) => format!("Option{}", type_name(t)),
+        idl::IdlType::Vec(t) => format!("Vec{}", type_name(t)),
+        idl::IdlType::Array(t, size) => format!("Array{}{}", type_name(t), size),
+        // e.g. "U64", "PublicKey"
+        _ => format!("{:?}", ty),
+    }
+}
@@ -0,0 +1,306 @@
+use anchor_syn::idl::{
+    EnumFields, Idl, IdlAccountItem, IdlField, IdlInstruction, IdlType, IdlTypeDefinition,
+    IdlTypeDefinitionTy,
+};
+use anyhow::{anyhow, Context, Result};
+use heck::{CamelCase, SnakeCase};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Generates a Go package with typed wrappers for the instructions of an Anchor program,
+/// the package is written to `./<name>/<name>.go` and can be imported as `"./<name>"`.
+pub fn import(idl_path: &Path, name: Option<&str>) -> Result<()> {
+    let idl_str = fs::read_to_string(idl_path).context("read idl error")?;
+    let idl: Idl = serde_json::from_str(&idl_str).context("parse idl error")?;
+    let pkg_name = name
+        .map(|x| x.to_owned())
+        .unwrap_or_else(|| idl.name.to_snake_case());
+    let code = go_client(&idl, &pkg_name)?;
+
+    fs::create_dir_all(&pkg_name)?;
+    let out_path = Path::new(&pkg_name).join(format!("{}.go", pkg_name));
+    fs::write(&out_path, code).context("write go package error")?;
+    println!("Generated {}", out_path.to_string_lossy());
+    Ok(())
+}
+
+fn go_client(idl: &Idl, pkg_name: &str) -> Result<String> {
+    let mut code = format!(
+        r#"// Code generated by `golana idl import`, DO NOT EDIT.
+
+// Package {0} calls the instructions of the Anchor program "{1}"
+package {0}
+
+import (
+	. "solana"
+)
+"#,
+        pkg_name, idl.name
+    );
+    for def in idl.types.iter().chain(idl.accounts.iter()) {
+        write_type_def(&mut code, def)?;
+    }
+    for ix in idl.instructions.iter() {
+        write_instruction(&mut code, ix)?;
+    }
+    Ok(code)
+}
+
+fn write_type_def(code: &mut String, def: &IdlTypeDefinition) -> Result<()> {
+    let name = type_name(&def.name)?;
+    match &def.ty {
+        IdlTypeDefinitionTy::Struct { fields } => write_struct(code, "", &name, fields),
+        // An enum is an interface implemented by the structs of the variants, see the
+        // Golana docs about enums
+        IdlTypeDefinitionTy::Enum { variants } => {
+            let marker = format!("is{}", name);
+            write!(code, "\ntype {} interface {{\n\t{}()\n}}\n", name, marker)?;
+            for variant in variants.iter() {
+                let variant_name = type_name(&format!("{}_{}", name, variant.name))?;
+                let fields = match &variant.fields {
+                    None => vec![],
+                    Some(EnumFields::Named(fields)) => fields.clone(),
+                    Some(EnumFields::Tuple(types)) => types
+                        .iter()
+                        .enumerate()
+                        .map(|(i, ty)| IdlField {
+                            name: format!("f{}", i),
+                            docs: None,
+                            ty: ty.clone(),
+                        })
+                        .collect(),
+                };
+                write_struct(code, "", &variant_name, &fields)?;
+                write!(code, "\nfunc (v {}) {}() {{}}\n", variant_name, marker)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+fn write_struct(code: &mut String, doc: &str, name: &str, fields: &[IdlField]) -> Result<()> {
+    let fields = fields
+        .iter()
+        .map(|f| Ok((f.name.to_camel_case(), go_type(&f.ty)?)))
+        .collect::<Result<Vec<(String, String)>>>()?;
+    write_fields(code, doc, name, &fields)
+}
+
+// The fields are aligned the way gofmt does it
+fn write_fields(
+    code: &mut String,
+    doc: &str,
+    name: &str,
+    fields: &[(String, String)],
+) -> Result<()> {
+    code.push('\n');
+    if !doc.is_empty() {
+        write!(code, "// {}\n", doc)?;
+    }
+    write!(code, "type {} struct {{\n", name)?;
+    let width = fields.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    for (name, ty) in fields.iter() {
+        write!(code, "\t{:width$} {}\n", name, ty, width = width)?;
+    }
+    code.push_str("}\n");
+    Ok(())
+}
+
+fn write_instruction(code: &mut String, ix: &IdlInstruction) -> Result<()> {
+    let name = type_name(&ix.name)?;
+    let mut accounts = vec![];
+    flatten_accounts(&ix.accounts, "", &mut accounts);
+
+    let account_fields: Vec<(String, String)> = accounts
+        .iter()
+        .map(|(field, _, _)| (field.clone(), "Account".to_owned()))
+        .collect();
+    write_fields(
+        code,
+        &format!("The accounts of {}", name),
+        &format!("{}Accounts", name),
+        &account_fields,
+    )?;
+
+    let args_param = match ix.args.is_empty() {
+        true => String::new(),
+        false => {
+            write_struct(
+                code,
+                &format!("The args of {}", name),
+                &format!("{}Args", name),
+                &ix.args,
+            )?;
+            format!(" args {}Args,", name)
+        }
+    };
+
+    let metas: String = accounts
+        .iter()
+        .map(|(field, is_signer, is_mut)| {
+            format!("\t\t{{accounts.{}, {}, {}}},\n", field, is_signer, is_mut)
+        })
+        .collect();
+    let data = match ix.args.is_empty() {
+        true => format!(
+            "\tsighash := AnchorSighash(\"{}\")\n\tdata := sighash[:]\n",
+            ix.name.to_snake_case()
+        ),
+        false => format!(
+            "\tdata, err := AnchorIxData(\"{}\", args)\n\tif err != nil {{\n\t\treturn err\n\t}}\n",
+            ix.name.to_snake_case()
+        ),
+    };
+    write!(
+        code,
+        r#"
+// Calls {0} of the program
+// signerSeeds: used when the singers are PDAs, pass nil if no signer is PDA
+func {0}(program Program, accounts *{0}Accounts,{1} signerSeeds []SeedBump) error {{
+{2}	metas := []AccountMeta{{
+{3}	}}
+	return Invoke(program, metas, data, signerSeeds)
+}}
+"#,
+        name, args_param, data, metas
+    )?;
+    Ok(())
+}
+
+/// Composite accounts are flattened, with the name of the group as the prefix
+fn flatten_accounts(items: &[IdlAccountItem], prefix: &str, out: &mut Vec<(String, bool, bool)>) {
+    for item in items.iter() {
+        match item {
+            IdlAccountItem::IdlAccount(acc) => out.push((
+                format!("{}{}", prefix, acc.name.to_camel_case()),
+                acc.is_signer,
+                acc.is_mut,
+            )),
+            IdlAccountItem::IdlAccounts(accs) => flatten_accounts(
+                &accs.accounts,
+                &format!("{}{}", prefix, accs.name.to_camel_case()),
+                out,
+            ),
+        }
+    }
+}
+
+fn type_name(name: &str) -> Result<String> {
+    let name = name.to_camel_case();
+    // The Golana checker treats any type named Ix... as an instruction
+    if name.starts_with("Ix") {
+        return Err(anyhow!("type name '{}' is reserved for instructions", name));
+    }
+    Ok(name)
+}
+
+fn go_type(ty: &IdlType) -> Result<String> {
+    let t = match ty {
+        IdlType::Bool => "bool".to_owned(),
+        IdlType::U8 => "uint8".to_owned(),
+        IdlType::I8 => "int8".to_owned(),
+        IdlType::U16 => "uint16".to_owned(),
+        IdlType::I16 => "int16".to_owned(),
+        IdlType::U32 => "uint32".to_owned(),
+        IdlType::I32 => "int32".to_owned(),
+        IdlType::F32 => "float32".to_owned(),
+        IdlType::U64 => "uint64".to_owned(),
+        IdlType::I64 => "int64".to_owned(),
+        IdlType::F64 => "float64".to_owned(),
+        // Go has no 128/256 bit integers, the little-endian bytes have the same layout
+        IdlType::U128 | IdlType::I128 => "[16]uint8".to_owned(),
+        IdlType::U256 | IdlType::I256 => "[32]uint8".to_owned(),
+        IdlType::Bytes => "[]byte".to_owned(),
+        IdlType::String => "string".to_owned(),
+        IdlType::PublicKey => "PublicKey".to_owned(),
+        IdlType::Defined(name) if !name.contains('<') => type_name(name)?,
+        IdlType::Option(inner) => match inner.as_ref() {
+            IdlType::Option(_) => return Err(anyhow!("nested Option is not supported")),
+            _ => format!("*{}", go_type(inner)?),
+        },
+        IdlType::Vec(inner) => format!("[]{}", go_type(inner)?),
+        IdlType::Array(inner, len) => format!("[{}]{}", len, go_type(inner)?),
+        _ => return Err(anyhow!("type {:?} is not supported", ty)),
+    };
+    Ok(t)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    const IDL: &str = r#"{
+        "version": "0.1.0",
+        "name": "order_book",
+        "instructions": [{
+            "name": "placeOrder",
+            "accounts": [{ "name": "book", "isMut": true, "isSigner": false }],
+            "args": [
+                { "name": "side", "type": { "defined": "Side" } },
+                { "name": "amount", "type": "u64" }
+            ]
+        }],
+        "types": [{
+            "name": "Side",
+            "type": {
+                "kind": "enum",
+                "variants": [
+                    { "name": "Bid" },
+                    { "name": "Ask", "fields": [{ "name": "limit", "type": "u64" }] }
+                ]
+            }
+        }]
+    }"#;
+
+    const MAIN: &str = r#"package main
+
+import (
+	"./order_book"
+	. "solana"
+)
+
+type IxPlace struct {
+	book    Account `account:"mut"`
+	program Program
+	limit   uint64
+}
+
+func (ix *IxPlace) Process() {
+	accounts := order_book.PlaceOrderAccounts{Book: ix.book}
+	args := order_book.PlaceOrderArgs{Side: order_book.SideAsk{Limit: ix.limit}, Amount: 1}
+	AbortOnError(order_book.PlaceOrder(ix.program, &accounts, args, nil))
+}
+
+func main() {
+	Execute()
+}
+"#;
+
+    #[test]
+    fn enum_arg_of_imported_package() {
+        let idl: Idl = serde_json::from_str(IDL).unwrap();
+        let dir = std::env::temp_dir().join(format!("golana_import_{}", std::process::id()));
+        fs::create_dir_all(dir.join("order_book")).unwrap();
+        fs::write(
+            dir.join("order_book").join("order_book.go"),
+            go_client(&idl, "order_book").unwrap(),
+        )
+        .unwrap();
+        fs::write(dir.join("main.go"), MAIN).unwrap();
+
+        let lib = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("go");
+        let reader = go_engine::SourceReader::local_fs(lib, dir.clone());
+        let bc = go_engine::Engine::new()
+            .compile(&reader, Path::new("./main.go"), true, false, false)
+            .unwrap_or_else(|e| panic!("compile error: {}", e));
+        fs::remove_dir_all(&dir).unwrap();
+
+        // The enum is only used by the imported package, Serialize() needs its metadata
+        let tx_meta = golana::check(&bc).unwrap();
+        let side = tx_meta.enums.iter().find(|x| x.name == "Side").unwrap();
+        let variants: Vec<&str> = side.variants.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(variants, ["SideBid", "SideAsk"]);
+    }
+}
@@ -7,6 +7,7 @@ mod build;
 mod config;
 mod deploy;
 mod idl;
+mod import;
 mod init;
 mod template;
 mod test;
@@ -60,6 +61,23 @@ enum Commands {
     },
     // /// Run the test script
     Test {},
+
+    /// IDL related commands
+    Idl {
+        #[command(subcommand)]
+        command: IdlCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum IdlCommands {
+    /// Generate a Go package to call an Anchor program, from the program's IDL
+    Import {
+        path: PathBuf,
+        /// The name of the Go package, defaults to the name of the program
+        #[arg(short, long)]
+        name: Option<String>,
+    },
 }
 
 fn main() {
@@ -128,10 +146,12 @@ fn processor() -> Result<()> {
                 println!("Golana project already initialized");
                 Ok(())
             }
+            Commands::Idl { command } => idl_command(command),
         }
     } else {
         match &cli.command.unwrap() {
             Commands::Init { name } => init::init(name),
+            Commands::Idl { command } => idl_command(command),
             _ => {
                 println!("No Golana.toml found in current directory");
                 Ok(())
@@ -139,3 +159,9 @@ fn processor() -> Result<()> {
         }
     }
 }
+
+fn idl_command(command: &IdlCommands) -> Result<()> {
+    match command {
+        IdlCommands::Import { path, name } => import::import(path, name.as_deref()),
+    }
+}
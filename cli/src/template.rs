@@ -97,7 +97,7 @@ func (ix *IxBusiness) Process() {
 
 // This is the entry point of the program
 func main() {
-    GetIx().Process()
+    Execute()
 } 
 "#
     .to_owned()
@@ -114,18 +114,59 @@ pub fn idl_ts(idl: &Idl) -> Result<String> {
         acc.name = acc.name.to_mixed_case();
     }
     let idl_json = serde_json::to_string_pretty(&idl)?;
+    let type_name = idl.name.to_camel_case();
     Ok(format!(
-        r#"export type {} = {};
+        r#"{}export type {} = {};
 
 export const IDL: {} = {};
-"#,
-        idl.name.to_camel_case(),
+{}"#,
+        accounts_imports_ts(&idl),
+        type_name,
+        idl_json,
+        type_name,
         idl_json,
-        idl.name.to_camel_case(),
-        idl_json
+        accounts_decoders_ts(&idl, &type_name),
     ))
 }
 
+fn accounts_imports_ts(idl: &Idl) -> &'static str {
+    if idl.accounts.is_empty() {
+        ""
+    } else {
+        "import { AccountsCoder, IdlAccounts } from \"golana\";\n\n"
+    }
+}
+
+// Typed decoders of the data accounts
+fn accounts_decoders_ts(idl: &Idl, type_name: &str) -> String {
+    if idl.accounts.is_empty() {
+        return String::new();
+    }
+    let decoders: Vec<String> = idl
+        .accounts
+        .iter()
+        .map(|acc| {
+            format!(
+                r#"
+export function decode{0}(data: Buffer): IdlAccounts<{1}>["{2}"] {{
+  return ACCOUNTS_CODER.decode("{2}", data);
+}}
+"#,
+                acc.name.to_camel_case(),
+                type_name,
+                acc.name,
+            )
+        })
+        .collect();
+    format!(
+        r#"
+export const ACCOUNTS_CODER = new AccountsCoder<{}>(IDL);
+{}"#,
+        type_name,
+        decoders.join("")
+    )
+}
+
 pub fn gitignore() -> String {
     r#"target
 node_modules"#
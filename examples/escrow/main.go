@@ -3,6 +3,7 @@
 package main
 
 import (
+	"errors"
 	. "solana"
 	"token"
 )
@@ -116,14 +117,27 @@ type IxCancel struct {
 
 	tokenProgram Program
 
+	// The data of escrowAccount, loaded before Process is called
+	escrowAccount_data *EscrowAccountData
+
 	escrowBump uint8
 }
 
-func (ix *IxCancel) Process() {
-	data := ix.escrowAccount.Data().(*EscrowAccountData)
-	Assert(*ix.initializer.Key() == data.initializerKey, "")
-	Assert(*ix.initializerDepositTokenAccount.Key() == data.initializerDepositTokenAccount, "")
+// Validate is called before Process, and the instruction is aborted if it returns an error
+func (ix *IxCancel) Validate() error {
+	data := ix.escrowAccount_data
+	if *ix.initializer.Key() != data.initializerKey {
+		return errors.New("initializer doesn't match")
+	}
+	if *ix.initializerDepositTokenAccount.Key() != data.initializerDepositTokenAccount {
+		return errors.New("initializerDepositTokenAccount doesn't match")
+	}
+	// The bump is passed in, so checking the PDA is cheap
+	return VerifyPda(ix.vaultAuthority, ESCROW_PDA_SEED, ix.escrowBump)
+}
 
+func (ix *IxCancel) Process() {
+	data := ix.escrowAccount_data
 	authority_seeds := []SeedBump{{ESCROW_PDA_SEED, ix.escrowBump}}
 
 	AbortOnError(token.Transfer(
@@ -142,5 +156,5 @@ func (ix *IxCancel) Process() {
 
 // This is the entry point of the program
 func main() {
-	GetIx().Process()
+	Execute()
 }
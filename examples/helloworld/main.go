@@ -42,7 +42,7 @@ type userData struct {
 func (ix *IxInit) Process() {
 	// On the client side, the userAccount is just a newly generated keypair
 	// we now initialize it on chain
-	ix.userAccount.Create(ix.user, 512, nil)
+	ix.userAccount.Create(ix.user, SpaceOf(userData{}), nil)
 
 	data := new(userData)
 	// set the auth of userAccount as the user
@@ -70,13 +70,11 @@ func (ix *IxGreet) Process() {
 
 	fmt2.Println("Hello", ix.names, "you have been greeted", data.greetCount, "times", "arrayTest", ix.arrayTest, "bufferTest", ix.buffer, "pk", ix.pk)
 
-	// Increment the greetCount
+	// Increment the greetCount, it's saved automatically when Process returns
 	data.greetCount++
-	ix.userAccount.SaveData(data)
-
 }
 
 // This is the entry point of the program
 func main() {
-	GetIx().Process()
+	Execute()
 }
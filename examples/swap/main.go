@@ -8,5 +8,5 @@ import (
 
 // This is the entry point of the program
 func main() {
-	solana.GetIx().Process()
+	solana.Execute()
 }
@@ -1,8 +1,12 @@
+use crate::codec;
 use crate::errors::*;
 use anchor_lang::prelude::*;
 use borsh::{BorshDeserialize, BorshSerialize};
 use go_vm::{types::PackageObj, *};
 
+// The helpers of the checker return the bare error code, `Error` is too big to be passed around
+type CheckResult<T> = std::result::Result<T, GolError>;
+
 #[derive(BorshDeserialize, BorshSerialize, Debug, Clone, PartialEq, Eq)]
 pub enum AccessMode {
     None,
@@ -20,12 +24,47 @@ impl AccessMode {
     }
 }
 
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
+pub struct DataMeta {
+    pub name: String,
+    pub meta: types::Meta,
+    pub discriminator: [u8; codec::DISCRIMINATOR_LEN],
+    // The space required by the account to store the data, including the discriminator
+    pub space: u64,
+    // Declared with `data:"T,zerocopy"`, the fields are accessed in place
+    pub zero_copy: bool,
+    // The offsets of the top level fields after the discriminator, for zero_copy only
+    pub field_offsets: Vec<u64>,
+    // Set by `data:"T,version=N"`, it's part of the discriminator
+    pub version: u8,
+    // The old versions of the type still kept in the program, declared as `<T>V<k>`
+    pub old_versions: Vec<OldDataMeta>,
+}
+
+impl DataMeta {
+    /// The name the data is stored under, which decides the discriminator
+    pub fn stored_name(&self) -> String {
+        codec::versioned_name(&self.name, self.version)
+    }
+}
+
+/// An old version of a data type, which is converted to the current one by its
+/// `Migrate() interface{}` method
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
+pub struct OldDataMeta {
+    pub version: u8,
+    pub meta: types::Meta,
+    pub discriminator: [u8; codec::DISCRIMINATOR_LEN],
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
 pub struct AccMeta {
     pub name: String,
     pub is_signer: bool,
     pub is_mut: bool,
-    pub data_meta: Option<types::Meta>,
+    pub data_meta: Option<DataMeta>,
+    // The index of the account this one is allowed to be the same as, set by `dup=<field>`
+    pub dup: Option<usize>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
@@ -34,7 +73,11 @@ pub struct IxMeta {
     pub gos_meta: types::Meta,
     pub process_method: types::FunctionKey,
     pub process_method_index: usize,
+    // The optional `Validate() error` method, called before Process
+    pub validate_method: Option<types::FunctionKey>,
     pub accounts: Vec<AccMeta>,
+    // The account indices of the data fields, i.e. `<account>_data *T`
+    pub data_fields: Vec<usize>,
     pub args: Vec<(String, types::Meta)>,
 }
 
@@ -44,9 +87,11 @@ impl IxMeta {
         gos_meta: types::Meta,
         account: &types::Meta,
         program: &types::Meta,
+        error: &types::Meta,
         pkg: &types::PackageObj,
-        metas: &types::MetadataObjs,
+        bc: &Bytecode,
     ) -> Result<IxMeta> {
+        let (metas, funcs) = (&bc.objects.metas, &bc.objects.functions);
         let (methods, inner_meta) = metas[gos_meta.key].as_named();
         let process_method_index = methods
             .mapping
@@ -58,11 +103,13 @@ impl IxMeta {
             .pointer_recv
             .then_some(method_desc.func.unwrap())
             .ok_or(error!(GolError::NonPointerReceiver))?;
+        let validate_method = Self::get_validate_method(methods, error, metas, funcs)?;
 
         // Build struct fields
         let fields = metas[inner_meta.key].as_struct().infos();
         let mut i = 0;
         let mut accounts: Vec<(AccMeta, &str)> = vec![];
+        let mut dup_names = vec![];
 
         // First, get all AccountInfo
         while i < fields.len() {
@@ -70,8 +117,9 @@ impl IxMeta {
             let name = &fields[i].name;
             let account_tag = &fields[i].lookup_tag("account");
             let (is_signer, is_mut) = Self::is_signer_or_mut(account_tag);
+            let dup_name = Self::get_dup(account_tag);
             let data_tag = &fields[i].lookup_tag("data");
-            let data_meta = Self::get_data_type(data_tag, pkg)?;
+            let data_meta = Self::get_data_type(data_tag, pkg, metas, funcs)?;
             if meta.key == account.key || meta.key == program.key {
                 if meta.ptr_depth != 0 {
                     return Err(error!(GolError::PointerAccount));
@@ -82,22 +130,72 @@ impl IxMeta {
                         is_signer,
                         is_mut,
                         data_meta,
+                        dup: None,
                     },
                     &fields[i].name,
                 ));
+                dup_names.push(dup_name);
                 i += 1;
             } else {
                 break;
             }
         }
 
+        // Resolve the `dup=<field>` allowances, which may refer to any other account
+        for (i, dup_name) in dup_names.into_iter().enumerate() {
+            if let Some(dup_name) = dup_name {
+                let index = accounts
+                    .iter()
+                    .position(|(_, name)| *name == dup_name)
+                    .filter(|index| *index != i)
+                    .ok_or(error!(GolError::DupAccountNotFound))?;
+                // Both would write the data back to the same account
+                if accounts[i].0.data_meta.is_some() || accounts[index].0.data_meta.is_some() {
+                    return Err(error!(GolError::DupAccountWithData));
+                }
+                accounts[i].0.dup = Some(index);
+            }
+        }
+
+        // Then the data of the accounts, declared as `<account>_data *T`
+        let mut data_fields = vec![];
+        while i < fields.len() {
+            match Self::data_account(&fields[i].name, &accounts) {
+                Some(index) => {
+                    let meta = &fields[i].meta;
+                    let data_meta = accounts[index]
+                        .0
+                        .data_meta
+                        .as_ref()
+                        .ok_or(error!(GolError::DataTypeNotSpecified))?;
+                    if meta.ptr_depth != 1 {
+                        return Err(error!(GolError::NonPointerDataDeclare));
+                    }
+                    if meta.key != data_meta.meta.key {
+                        return Err(error!(GolError::DataTypeMismatch));
+                    }
+                    if data_meta.zero_copy {
+                        return Err(error!(GolError::ZeroCopyDataDeclare));
+                    }
+                    data_fields.push(index);
+                    i += 1;
+                }
+                None => break,
+            }
+        }
+
         // Then arguments
         let mut args = vec![];
         while i < fields.len() {
             let meta = &fields[i].meta;
-            if meta.is_type || meta.ptr_depth != 0 {
+            // A pointer argument is optional, a nil pointer stands for None
+            if meta.is_type || meta.ptr_depth > 1 {
                 return Err(error!(GolError::WrongArgType));
             }
+            // `<account>_data` after other arguments, it would be taken for the data
+            if Self::data_account(&fields[i].name, &accounts).is_some() {
+                return Err(error!(GolError::AccountNamePrefixReserved));
+            }
             // todo: more checks
             args.push((fields[i].name.clone(), meta.clone()));
             i += 1;
@@ -108,11 +206,48 @@ impl IxMeta {
             gos_meta,
             process_method,
             process_method_index,
+            validate_method,
             accounts: accounts.into_iter().map(|(acc, _)| acc).collect(),
+            data_fields,
             args,
         })
     }
 
+    /// Finds the optional `Validate` method, which has to be `func (ix *IxFoo) Validate() error`
+    fn get_validate_method(
+        methods: &types::Methods,
+        error: &types::Meta,
+        metas: &types::MetadataObjs,
+        funcs: &types::FunctionObjs,
+    ) -> CheckResult<Option<types::FunctionKey>> {
+        let index = match methods
+            .mapping
+            .iter()
+            .find_map(|(name, index)| (name == "Validate").then_some(*index as usize))
+        {
+            Some(index) => index,
+            None => return Ok(None),
+        };
+        let method_desc = methods.members[index].borrow();
+        if !method_desc.pointer_recv {
+            return Err(GolError::NonPointerReceiver);
+        }
+        let func = method_desc.func.unwrap();
+        let sig = metas[funcs[func].meta.key].as_signature();
+        if !sig.params.is_empty() || sig.variadic.is_some() || sig.results != [*error] {
+            return Err(GolError::BadValidateSignature);
+        }
+        Ok(Some(func))
+    }
+
+    /// The index of the account whose data a field named `<account>_data` is
+    fn data_account(name: &str, accounts: &[(AccMeta, &str)]) -> Option<usize> {
+        let prefix = name.strip_suffix("_data")?;
+        accounts
+            .iter()
+            .position(|(_, acc_name)| *acc_name == prefix)
+    }
+
     fn is_signer_or_mut(tag: &Option<String>) -> (bool, bool) {
         if let Some(tag) = tag {
             let tags: Vec<&str> = tag.split(',').map(|x| x.trim()).collect();
@@ -123,28 +258,225 @@ impl IxMeta {
         (false, false)
     }
 
-    fn get_data_type(tag: &Option<String>, pkg: &PackageObj) -> Result<Option<types::Meta>> {
+    fn get_dup(tag: &Option<String>) -> Option<String> {
+        tag.as_ref()?
+            .split(',')
+            .find_map(|x| x.trim().strip_prefix("dup="))
+            .map(|x| x.trim().to_owned())
+    }
+
+    fn get_data_type(
+        tag: &Option<String>,
+        pkg: &PackageObj,
+        metas: &types::MetadataObjs,
+        funcs: &types::FunctionObjs,
+    ) -> Result<Option<DataMeta>> {
         match tag {
-            Some(t) => {
-                let index = pkg
-                    .member_index(&t)
-                    .ok_or(error!(GolError::DataTypeNotFound))?;
-                let meta = pkg.member(*index);
-                if meta.typ() != types::ValueType::Metadata {
-                    return Err(error!(GolError::DataTypeNotFound));
+            Some(tag) => {
+                // `data:"T"`, with options like `data:"T,zerocopy,version=2"`
+                let mut parts = tag.split(',').map(|x| x.trim());
+                let t = parts.next().unwrap_or_default().to_owned();
+                let mut zero_copy = false;
+                let mut version = 0;
+                for part in parts {
+                    if part == "zerocopy" {
+                        zero_copy = true;
+                    } else if let Some(v) = part.strip_prefix("version=") {
+                        version = v
+                            .trim()
+                            .parse()
+                            .map_err(|_| error!(GolError::BadDataVersion))?;
+                    } else {
+                        return Err(error!(GolError::BadDataTag));
+                    }
+                }
+                // ReadField and WriteField work on the account data, which isn't migrated
+                if zero_copy && version > 0 {
+                    return Err(error!(GolError::ZeroCopyVersioned));
                 }
-                Ok(Some(meta.as_metadata().clone()))
+                let meta =
+                    Self::get_package_type(&t, pkg).ok_or(error!(GolError::DataTypeNotFound))?;
+                let old_versions = (0..version)
+                    .filter_map(|k| {
+                        let meta = Self::get_package_type(&format!("{}V{}", t, k), pkg)?;
+                        Some(Self::get_old_version(&t, k, meta, metas, funcs))
+                    })
+                    .collect::<Result<Vec<OldDataMeta>>>()?;
+                Ok(Some(DataMeta {
+                    name: t.clone(),
+                    meta,
+                    discriminator: codec::account_discriminator(&codec::versioned_name(
+                        &t, version,
+                    )),
+                    // Computed after all the enums are known
+                    space: 0,
+                    zero_copy,
+                    field_offsets: vec![],
+                    version,
+                    old_versions,
+                }))
             }
             None => Ok(None),
         }
     }
+
+    fn get_package_type(name: &str, pkg: &PackageObj) -> Option<types::Meta> {
+        let member = pkg.member(*pkg.member_index(name)?);
+        (member.typ() == types::ValueType::Metadata).then(|| *member.as_metadata())
+    }
+
+    /// The old version has to have a `func (d *TV1) Migrate() interface{}` method
+    fn get_old_version(
+        name: &str,
+        version: u8,
+        meta: types::Meta,
+        metas: &types::MetadataObjs,
+        funcs: &types::FunctionObjs,
+    ) -> Result<OldDataMeta> {
+        let methods = match &metas[meta.key] {
+            types::MetadataType::Named(methods, _) => methods,
+            _ => return Err(error!(GolError::BadMigrateSignature)),
+        };
+        let index = methods
+            .mapping
+            .iter()
+            .find_map(|(name, index)| (name == "Migrate").then_some(*index as usize))
+            .ok_or(error!(GolError::BadMigrateSignature))?;
+        let method_desc = methods.members[index].borrow();
+        if !method_desc.pointer_recv {
+            return Err(error!(GolError::NonPointerReceiver));
+        }
+        let sig = metas[funcs[method_desc.func.unwrap()].meta.key].as_signature();
+        let returns_any = sig.results.len() == 1 && {
+            let result = &sig.results[0];
+            result.ptr_depth == 0
+                && matches!(&metas[result.key], types::MetadataType::Interface(fields)
+                    if fields.infos().is_empty())
+        };
+        if !sig.params.is_empty() || sig.variadic.is_some() || !returns_any {
+            return Err(error!(GolError::BadMigrateSignature));
+        }
+        Ok(OldDataMeta {
+            version,
+            meta,
+            discriminator: codec::account_discriminator(&codec::versioned_name(name, version)),
+        })
+    }
+}
+
+/// The version of the TxMeta layout, the loader keeps the TxMeta of a program in its memory
+/// dump, so a program finalized with a different layout has to be deployed again.
+/// Version 0 is the layout before the account data had a discriminator.
+pub const META_VERSION: u32 = 1;
+
+/// An interface used in args or account data works as an enum, its variants are the
+/// structs that implement it, in the order they are declared.
+#[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
+pub struct EnumMeta {
+    pub name: String,
+    pub meta: types::Meta,
+    pub variants: Vec<(String, types::Meta)>,
+}
+
+impl EnumMeta {
+    fn new(name: &str, meta: types::Meta, bc: &Bytecode) -> CheckResult<EnumMeta> {
+        let metas = &bc.objects.metas;
+        let methods: Vec<&String> = match &metas[meta.underlying(metas).key] {
+            types::MetadataType::Interface(fields) => {
+                fields.infos().iter().map(|x| &x.name).collect()
+            }
+            _ => unreachable!(),
+        };
+        if methods.is_empty() {
+            return Err(GolError::EmptyEnumInterface);
+        }
+
+        // Only the structs declared in the package of the interface count, so that the
+        // variants don't change with the other packages the program imports
+        let pkg = bc
+            .objects
+            .packages
+            .iter()
+            .find(|pkg| declared_types(pkg).iter().any(|(_, m)| m.key == meta.key))
+            .ok_or(GolError::NoEnumVariant)?;
+        let mut variants = vec![];
+        for (name, vmeta) in declared_types(pkg) {
+            if let types::MetadataType::Named(vmethods, inner) = &metas[vmeta.key] {
+                let is_struct = matches!(&metas[inner.key], types::MetadataType::Struct(_));
+                // The struct itself, rather than a pointer to it, has to implement the
+                // interface, so that it can be stored in the interface when deserialized
+                let implements = methods.iter().all(|m| {
+                    vmethods
+                        .mapping
+                        .get(*m)
+                        .map(|i| !vmethods.members[*i as usize].borrow().pointer_recv)
+                        .unwrap_or(false)
+                });
+                if is_struct && implements {
+                    variants.push((name.clone(), vmeta));
+                }
+            }
+        }
+        if variants.is_empty() {
+            return Err(GolError::NoEnumVariant);
+        }
+        if variants.len() > u8::MAX as usize + 1 {
+            return Err(GolError::TooManyEnumVariants);
+        }
+        Ok(EnumMeta {
+            name: name.to_owned(),
+            meta,
+            variants,
+        })
+    }
+
+    /// Find all the interfaces used as enums in the type
+    fn collect(meta: &types::Meta, bc: &Bytecode, enums: &mut Vec<EnumMeta>) -> CheckResult<()> {
+        let objs = &bc.objects;
+        match &objs.metas[meta.key] {
+            types::MetadataType::Array(elem, _) | types::MetadataType::Slice(elem) => {
+                Self::collect(elem, bc, enums)
+            }
+            types::MetadataType::Map(kmeta, vmeta) => {
+                Self::collect(kmeta, bc, enums)?;
+                Self::collect(vmeta, bc, enums)
+            }
+            types::MetadataType::Struct(fields) => fields
+                .infos()
+                .iter()
+                .try_for_each(|f| Self::collect(&f.meta, bc, enums)),
+            types::MetadataType::Named(_, inner) => match &objs.metas[inner.key] {
+                types::MetadataType::Interface(_) => {
+                    if enums.iter().any(|x| x.meta.key == meta.key) {
+                        return Ok(());
+                    }
+                    let mut enum_meta = *meta;
+                    enum_meta.ptr_depth = 0;
+                    let name = type_name(meta, objs).ok_or(GolError::WrongArgType)?;
+                    let enum_meta = EnumMeta::new(&name, enum_meta, bc)?;
+                    let variants: Vec<types::Meta> =
+                        enum_meta.variants.iter().map(|(_, m)| *m).collect();
+                    enums.push(enum_meta);
+                    variants
+                        .iter()
+                        .try_for_each(|m| Self::collect(m, bc, enums))
+                }
+                _ => Self::collect(inner, bc, enums),
+            },
+            _ => Ok(()),
+        }
+    }
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Debug, Clone)]
 pub struct TxMeta {
     pub iface_meta: types::Meta,
+    pub validator_meta: types::Meta,
+    pub migrator_meta: types::Meta,
     pub pub_key_meta: types::Meta,
     pub instructions: Vec<IxMeta>,
+    /// The enums used by the instructions and the types declared by the program
+    pub enums: Vec<EnumMeta>,
 }
 
 pub fn check(bc: &Bytecode) -> Result<TxMeta> {
@@ -152,6 +484,8 @@ pub fn check(bc: &Bytecode) -> Result<TxMeta> {
     let program_meta = get_solana_type_meta(bc, "Program").ok_or(error!(GolError::MetaNotFound))?;
 
     let mut iface_meta = None;
+    let mut validator_meta = None;
+    let mut migrator_meta = None;
     let mut pub_key_meta = None;
     let mut ix_details = Vec::new();
     for pkg in bc.objects.packages.iter() {
@@ -160,6 +494,14 @@ pub fn check(bc: &Bytecode) -> Result<TxMeta> {
             for (name, index) in pkg.member_indices() {
                 if name == "Ix" && pkg.member(*index).typ() == types::ValueType::Metadata {
                     iface_meta = Some(pkg.member(*index).as_metadata().clone());
+                } else if name == "Validator"
+                    && pkg.member(*index).typ() == types::ValueType::Metadata
+                {
+                    validator_meta = Some(*pkg.member(*index).as_metadata());
+                } else if name == "Migrator"
+                    && pkg.member(*index).typ() == types::ValueType::Metadata
+                {
+                    migrator_meta = Some(*pkg.member(*index).as_metadata());
                 } else if name == "PublicKey"
                     && pkg.member(*index).typ() == types::ValueType::Metadata
                 {
@@ -178,7 +520,11 @@ pub fn check(bc: &Bytecode) -> Result<TxMeta> {
         }
     }
 
-    let instructions = ix_details
+    let validator_meta = validator_meta.ok_or(error!(GolError::MetaNotFound))?;
+    let migrator_meta = migrator_meta.ok_or(error!(GolError::MetaNotFound))?;
+    let error_meta =
+        get_error_meta(&validator_meta, &bc.objects.metas).ok_or(error!(GolError::MetaNotFound))?;
+    let mut instructions = ix_details
         .into_iter()
         .map(|(name, meta, pkg)| {
             IxMeta::new(
@@ -186,18 +532,108 @@ pub fn check(bc: &Bytecode) -> Result<TxMeta> {
                 meta,
                 &account_meta,
                 &program_meta,
+                &error_meta,
                 pkg,
-                &bc.objects.metas,
+                bc,
             )
         })
         .collect::<Result<Vec<IxMeta>>>()?;
+
+    let mut enums = vec![];
+    for ix in instructions.iter() {
+        for (_, meta) in ix.args.iter() {
+            EnumMeta::collect(meta, bc, &mut enums)?;
+        }
+        for data_meta in ix.accounts.iter().filter_map(|x| x.data_meta.as_ref()) {
+            EnumMeta::collect(&data_meta.meta, bc, &mut enums)?;
+            for old in data_meta.old_versions.iter() {
+                EnumMeta::collect(&old.meta, bc, &mut enums)?;
+            }
+        }
+    }
+    // Serialize() and DataAs() can take any type of the program or of the packages it imports, e.g. the
+    // ones generated by `golana idl import`, so the enums of the declared types are collected
+    // as well. A type that can't be encoded is skipped, the codec rejects it when it's used.
+    for pkg in bc.objects.packages.iter() {
+        for (_, meta) in declared_types(pkg) {
+            let len = enums.len();
+            if EnumMeta::collect(&meta, bc, &mut enums).is_err() {
+                enums.truncate(len);
+            }
+        }
+    }
+
+    for data_meta in instructions
+        .iter_mut()
+        .flat_map(|ix| ix.accounts.iter_mut())
+        .filter_map(|x| x.data_meta.as_mut())
+    {
+        if data_meta.zero_copy {
+            data_meta.field_offsets = codec::field_offsets(&bc.objects.metas, &data_meta.meta)
+                .ok_or(error!(GolError::ZeroCopyNotFixedLayout))?;
+        }
+        let size = codec::max_size(&bc.objects.metas, &enums, &data_meta.meta, &[])
+            .ok_or(error!(GolError::UnboundedDataType))?;
+        data_meta.space = (codec::DISCRIMINATOR_LEN + size) as u64;
+    }
+
     Ok(TxMeta {
         iface_meta: iface_meta.unwrap(),
+        validator_meta,
+        migrator_meta,
         pub_key_meta: pub_key_meta.unwrap(),
         instructions,
+        enums,
     })
 }
 
+/// The `error` type, which `Validator.Validate` returns
+fn get_error_meta(validator: &types::Meta, metas: &types::MetadataObjs) -> Option<types::Meta> {
+    let (_, inner) = metas[validator.key].as_named();
+    match &metas[inner.key] {
+        types::MetadataType::Interface(fields) => {
+            let method = fields.infos().first()?;
+            metas[method.meta.key]
+                .as_signature()
+                .results
+                .first()
+                .copied()
+        }
+        _ => None,
+    }
+}
+
+/// The name of a type declared at package level
+pub fn type_name(meta: &types::Meta, objs: &types::VMObjects) -> Option<String> {
+    objs.packages.iter().find_map(|pkg| {
+        pkg.member_indices().iter().find_map(|(name, index)| {
+            let member = pkg.member(*index);
+            (member.typ() == types::ValueType::Metadata && member.as_metadata().key == meta.key)
+                .then(|| name.clone())
+        })
+    })
+}
+
+/// The types declared at the top level of the package, in the order they are declared.
+/// The code generator numbers the members of a package as it goes through the declarations,
+/// file by file in the order of the file names, so the index is the declaration position.
+fn declared_types(pkg: &PackageObj) -> Vec<(&String, types::Meta)> {
+    let mut decls: Vec<(types::OpIndex, &String, types::Meta)> = pkg
+        .member_indices()
+        .iter()
+        .filter_map(|(name, index)| {
+            let member = pkg.member(*index);
+            (member.typ() == types::ValueType::Metadata)
+                .then(|| (*index, name, *member.as_metadata()))
+        })
+        .collect();
+    decls.sort_by_key(|(index, ..)| *index);
+    decls
+        .into_iter()
+        .map(|(_, name, meta)| (name, meta))
+        .collect()
+}
+
 fn get_solana_type_meta(bc: &Bytecode, name: &str) -> Option<types::Meta> {
     let key = bc
         .objects
@@ -218,7 +654,29 @@ fn get_solana_type_meta(bc: &Bytecode, name: &str) -> Option<types::Meta> {
 mod tests {
     use super::*;
     use std::io::Read;
-    use std::path::Path;
+    use std::path::{Path, PathBuf};
+
+    /// Compiles a program against the Go libs of the cli
+    fn compile(src: String) -> Bytecode {
+        let (reader, path) =
+            go_engine::SourceReader::fs_lib_and_string(PathBuf::from("../cli/go"), src.into());
+        go_engine::Engine::new()
+            .compile(&reader, &path, true, false, false)
+            .unwrap_or_else(|e| panic!("compile error: {}", e))
+    }
+
+    /// Compiles a program made of the declarations and an empty main, and checks it.
+    /// The error is the error code, which compares to `GolError::X.into()`.
+    fn check_decls(decls: &str) -> std::result::Result<TxMeta, u32> {
+        let src = format!(
+            "package main\n\nimport . \"solana\"\n\n{}\n\nfunc main() {{}}\n",
+            decls
+        );
+        check(&compile(src)).map_err(|e| match e {
+            Error::AnchorError(e) => e.error_code_number,
+            Error::ProgramError(e) => panic!("unexpected error: {}", e),
+        })
+    }
 
     fn read_bytecode(full_name: &Path) -> Bytecode {
         let mut f = std::fs::OpenOptions::new()
@@ -238,4 +696,275 @@ mod tests {
         let xx = check(&bc);
         dbg!(&xx);
     }
+
+    #[test]
+    fn enum_variants_in_declaration_order() {
+        let meta = check_decls(
+            r#"
+type Side interface {
+	isSide()
+}
+
+type Zeta struct{}
+
+func (Zeta) isSide() {}
+
+// A pointer receiver doesn't make a variant
+type Ptr struct{}
+
+func (*Ptr) isSide() {}
+
+type Alpha struct {
+	limit uint64
+}
+
+func (Alpha) isSide() {}
+
+type IxTrade struct {
+	user Account `account:"signer"`
+	side Side
+}
+
+func (ix *IxTrade) Process() {}"#,
+        )
+        .unwrap();
+        let side = meta.enums.iter().find(|x| x.name == "Side").unwrap();
+        let names: Vec<&str> = side.variants.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["Zeta", "Alpha"]);
+    }
+
+    #[test]
+    fn data_space_from_max_len() {
+        let meta = check_decls(
+            r#"
+type Book struct {
+	names []string `max_len:"2, 4"`
+	count uint64   ``
+}
+
+type IxBook struct {
+	book Account `account:"mut" data:"Book"`
+}
+
+func (ix *IxBook) Process() {}"#,
+        )
+        .unwrap();
+        let data_meta = meta.instructions[0].accounts[0].data_meta.as_ref().unwrap();
+        // 8 bytes of discriminator, 4 + 2 * (4 + 4) for the names and 8 for the count
+        assert_eq!(data_meta.space, 36);
+
+        let err = check_decls(
+            r#"
+type Notes struct {
+	text string
+}
+
+type IxNotes struct {
+	notes Account `account:"mut" data:"Notes"`
+}
+
+func (ix *IxNotes) Process() {}"#,
+        )
+        .unwrap_err();
+        assert_eq!(err, GolError::UnboundedDataType.into());
+    }
+
+    #[test]
+    fn data_fields_after_accounts() {
+        let meta = check_decls(
+            r#"
+type Book struct {
+	count uint64
+}
+
+type IxBook struct {
+	book      Account `account:"mut" data:"Book"`
+	book_data *Book
+	book_size uint64
+}
+
+func (ix *IxBook) Process() {}"#,
+        )
+        .unwrap();
+        let ix = &meta.instructions[0];
+        assert_eq!(ix.data_fields, [0]);
+        assert_eq!(ix.args[0].0, "book_size");
+
+        // Not right after the accounts, it would be taken for an argument
+        let err = check_decls(
+            r#"
+type Book struct {
+	count uint64
+}
+
+type IxBook struct {
+	book      Account `account:"mut" data:"Book"`
+	book_size uint64
+	book_data *Book
+}
+
+func (ix *IxBook) Process() {}"#,
+        )
+        .unwrap_err();
+        assert_eq!(err, GolError::AccountNamePrefixReserved.into());
+    }
+
+    #[test]
+    fn validate_returns_error() {
+        let ix = |validate: &str| {
+            check_decls(&format!(
+                r#"
+type myError interface {{
+	Error() string
+}}
+
+type IxPay struct {{
+	payer Account `account:"signer"`
+}}
+
+func (ix *IxPay) Process() {{}}
+
+{}"#,
+                validate
+            ))
+        };
+        let meta = ix("func (ix *IxPay) Validate() error { return nil }").unwrap();
+        assert!(meta.instructions[0].validate_method.is_some());
+
+        // Another interface with the same methods isn't the builtin error
+        let err = ix("func (ix *IxPay) Validate() myError { return nil }").unwrap_err();
+        assert_eq!(err, GolError::BadValidateSignature.into());
+        let err = ix("func (ix IxPay) Validate() error { return nil }").unwrap_err();
+        assert_eq!(err, GolError::NonPointerReceiver.into());
+    }
+
+    #[test]
+    fn dup_allowed_without_data() {
+        let ix = |dest_tag: &str| {
+            check_decls(&format!(
+                r#"
+type Vault struct {{
+	amount uint64
+}}
+
+type IxMove struct {{
+	source Account `account:"mut" data:"Vault"`
+	dest   Account `{}`
+}}
+
+func (ix *IxMove) Process() {{}}"#,
+                dest_tag
+            ))
+        };
+        let err = ix(r#"account:"mut, dup=source" data:"Vault""#).unwrap_err();
+        assert_eq!(err, GolError::DupAccountWithData.into());
+        let err = ix(r#"account:"mut, dup=source""#).unwrap_err();
+        assert_eq!(err, GolError::DupAccountWithData.into());
+        let err = ix(r#"account:"mut, dup=src""#).unwrap_err();
+        assert_eq!(err, GolError::DupAccountNotFound.into());
+
+        let meta = check_decls(
+            r#"
+type IxMove struct {
+	source Account `account:"mut"`
+	dest   Account `account:"mut, dup=source"`
+}
+
+func (ix *IxMove) Process() {}"#,
+        )
+        .unwrap();
+        assert_eq!(meta.instructions[0].accounts[1].dup, Some(0));
+    }
+
+    #[test]
+    fn data_versions() {
+        let ix = |tag: &str, migrate: &str| {
+            check_decls(&format!(
+                r#"
+type CounterV0 struct {{
+	count uint32
+}}
+
+{}
+
+type Counter struct {{
+	count uint64
+}}
+
+type IxBump struct {{
+	counter Account `account:"mut" data:"{}"`
+}}
+
+func (ix *IxBump) Process() {{}}"#,
+                migrate, tag
+            ))
+        };
+        let migrate = "func (old *CounterV0) Migrate() interface{} { return &Counter{} }";
+        let meta = ix("Counter,version=2", migrate).unwrap();
+        let data_meta = meta.instructions[0].accounts[0].data_meta.as_ref().unwrap();
+        assert_eq!(data_meta.version, 2);
+        assert_eq!(data_meta.stored_name(), "CounterV2");
+        assert_eq!(
+            data_meta.discriminator,
+            codec::account_discriminator("CounterV2")
+        );
+        // CounterV1 isn't declared, so only version 0 can be migrated
+        let versions: Vec<u8> = data_meta.old_versions.iter().map(|x| x.version).collect();
+        assert_eq!(versions, [0]);
+        assert_eq!(
+            data_meta.old_versions[0].discriminator,
+            codec::account_discriminator("Counter")
+        );
+
+        let err = ix("Counter,version=1", "").unwrap_err();
+        assert_eq!(err, GolError::BadMigrateSignature.into());
+        let err = ix(
+            "Counter,version=1",
+            "func (old *CounterV0) Migrate() *Counter { return nil }",
+        )
+        .unwrap_err();
+        assert_eq!(err, GolError::BadMigrateSignature.into());
+        let err = ix("Counter,version=256", migrate).unwrap_err();
+        assert_eq!(err, GolError::BadDataVersion.into());
+        let err = ix("Counter,zerocopy,version=1", migrate).unwrap_err();
+        assert_eq!(err, GolError::ZeroCopyVersioned.into());
+        let err = ix("Counter,versoin=1", migrate).unwrap_err();
+        assert_eq!(err, GolError::BadDataTag.into());
+    }
+
+    #[test]
+    fn zero_copy_fixed_layout() {
+        let ix = |data: &str, fields: &str| {
+            check_decls(&format!(
+                r#"
+type Order struct {{
+	price uint64
+	side  uint8
+}}
+
+type Book struct {{
+	count uint32
+	bids  [4]Order
+	{}
+}}
+
+type IxPlace struct {{
+	book Account `account:"mut" data:"Book,zerocopy"`
+	{}
+}}
+
+func (ix *IxPlace) Process() {{}}"#,
+                data, fields
+            ))
+        };
+        let meta = ix("", "").unwrap();
+        let data_meta = meta.instructions[0].accounts[0].data_meta.as_ref().unwrap();
+        assert!(data_meta.zero_copy);
+        assert_eq!(data_meta.field_offsets, [0, 4]);
+
+        let err = ix("owner string", "").unwrap_err();
+        assert_eq!(err, GolError::ZeroCopyNotFixedLayout.into());
+        let err = ix("", "book_data *Book").unwrap_err();
+        assert_eq!(err, GolError::ZeroCopyDataDeclare.into());
+    }
 }
@@ -0,0 +1,796 @@
+use crate::EnumMeta;
+use borsh::{BorshDeserialize, BorshSerialize};
+use go_vm::types::*;
+use go_vm::{FfiCtx, Map};
+use std::fmt;
+use std::io::{Error, ErrorKind, Result, Write};
+
+pub const DISCRIMINATOR_LEN: usize = 8;
+
+/// Why `deserialize` rejected a buffer, it's carried by the returned `std::io::Error`
+/// and can be retrieved with `DecodeError::of`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The buffer ends in the middle of a value
+    Truncated,
+    /// A bool that is neither 0 nor 1
+    InvalidBool,
+    /// The length of a string, slice or map is more than what the rest of the buffer can hold
+    LengthOverflow,
+    /// Any other malformed value, e.g. a bad Option tag or enum variant
+    InvalidValue,
+}
+
+impl DecodeError {
+    pub fn of(e: &Error) -> DecodeError {
+        e.get_ref()
+            .and_then(|x| x.downcast_ref::<DecodeError>())
+            .copied()
+            .unwrap_or(DecodeError::InvalidValue)
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let msg = match self {
+            DecodeError::Truncated => "unexpected end of buffer",
+            DecodeError::InvalidBool => "invalid bool",
+            DecodeError::LengthOverflow => "length exceeds the buffer",
+            DecodeError::InvalidValue => "invalid value",
+        };
+        f.write_str(msg)
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+impl From<DecodeError> for Error {
+    fn from(e: DecodeError) -> Error {
+        Error::new(ErrorKind::InvalidData, e)
+    }
+}
+
+/// Returns the 8-byte discriminator stored in front of the account data, it's the same as
+/// what Anchor uses: sha256("account:<PascalCaseName>")[..8], so that the clients can decode
+/// Golana accounts with Anchor's coders.
+pub fn account_discriminator(name: &str) -> [u8; DISCRIMINATOR_LEN] {
+    let preimage = format!("account:{}", pascal_case(name));
+    let hash = solana_program::hash::hash(preimage.as_bytes()).to_bytes();
+    let mut disc = [0u8; DISCRIMINATOR_LEN];
+    disc.copy_from_slice(&hash[..DISCRIMINATOR_LEN]);
+    disc
+}
+
+/// The name a data type is stored under, the version is a part of it so that every version
+/// has its own discriminator: `T` for version 0, and `TV<N>` for version N.
+pub fn versioned_name(name: &str, version: u8) -> String {
+    match version {
+        0 => name.to_owned(),
+        _ => format!("{}V{}", name, version),
+    }
+}
+
+/// Serialize a Goscript value in standard borsh format, the layout is decided by the metadata
+/// instead of the value itself, so the result can be decoded by any borsh implementation.
+pub fn serialize<W: Write>(
+    ctx: &FfiCtx,
+    enums: &[EnumMeta],
+    val: &GosValue,
+    meta: &Meta,
+    writer: &mut W,
+) -> Result<()> {
+    match meta.ptr_depth {
+        0 => {}
+        // A nil-able pointer is serialized as an Option
+        1 => {
+            return match val.as_pointer() {
+                None => 0u8.serialize(writer),
+                Some(p) => {
+                    let pointee = p
+                        .deref(ctx.stack, &ctx.vm_objs.packages)
+                        .map_err(|e| Error::new(ErrorKind::InvalidData, e.as_str()))?;
+                    1u8.serialize(writer)?;
+                    serialize(ctx, enums, &pointee, &meta.unptr_to(), writer)
+                }
+            };
+        }
+        _ => return Err(unsupported()),
+    }
+    match &ctx.vm_objs.metas[meta.key] {
+        MetadataType::Bool => val.as_bool().serialize(writer),
+        MetadataType::Int => (*val.as_int() as i64).serialize(writer),
+        MetadataType::Int8 => val.as_int8().serialize(writer),
+        MetadataType::Int16 => val.as_int16().serialize(writer),
+        MetadataType::Int32 => val.as_int32().serialize(writer),
+        MetadataType::Int64 => val.as_int64().serialize(writer),
+        MetadataType::Uint => (*val.as_uint() as u64).serialize(writer),
+        MetadataType::Uint8 => val.as_uint8().serialize(writer),
+        MetadataType::Uint16 => val.as_uint16().serialize(writer),
+        MetadataType::Uint32 => val.as_uint32().serialize(writer),
+        MetadataType::Uint64 => val.as_uint64().serialize(writer),
+        MetadataType::Float32 => val.as_float32().into_inner().serialize(writer),
+        MetadataType::Float64 => val.as_float64().into_inner().serialize(writer),
+        MetadataType::Str => val.as_string().as_str().serialize(writer),
+        MetadataType::Array(elem, _) => {
+            for v in elements(ctx, val, elem).iter() {
+                serialize(ctx, enums, v, elem, writer)?;
+            }
+            Ok(())
+        }
+        MetadataType::Slice(elem) => {
+            let vals = elements(ctx, val, elem);
+            if vals.len() >= u32::MAX as usize {
+                return Err(Error::new(ErrorKind::InvalidData, "slice too long"));
+            }
+            (vals.len() as u32).serialize(writer)?;
+            for v in vals.iter() {
+                serialize(ctx, enums, v, elem, writer)?;
+            }
+            Ok(())
+        }
+        // The VM is built with `btree_map`, so the entries are always in key order
+        MetadataType::Map(kmeta, vmeta) => match val.as_map() {
+            Some((map, _)) => {
+                let data = map.borrow_data();
+                if data.len() >= u32::MAX as usize {
+                    return Err(Error::new(ErrorKind::InvalidData, "map too long"));
+                }
+                (data.len() as u32).serialize(writer)?;
+                for (k, v) in data.iter() {
+                    serialize(ctx, enums, k, kmeta, writer)?;
+                    serialize(ctx, enums, v, vmeta, writer)?;
+                }
+                Ok(())
+            }
+            None => 0u32.serialize(writer),
+        },
+        MetadataType::Struct(fields) => {
+            let field_vals = val.as_struct().0.borrow_fields();
+            for (info, v) in fields.infos().iter().zip(field_vals.iter()) {
+                serialize(ctx, enums, v, &info.meta, writer)?;
+            }
+            Ok(())
+        }
+        MetadataType::Named(_, inner) => match enums.iter().find(|x| x.meta.key == meta.key) {
+            Some(enum_meta) => serialize_enum(ctx, enums, val, enum_meta, writer),
+            None => serialize(ctx, enums, val, inner, writer),
+        },
+        _ => Err(unsupported()),
+    }
+}
+
+/// Deserialize a Goscript value of the type described by `meta` from standard borsh format.
+/// Only the canonical encoding is accepted, see `DecodeError` for how a buffer can be rejected.
+pub fn deserialize(
+    ctx: &FfiCtx,
+    enums: &[EnumMeta],
+    meta: &Meta,
+    buf: &mut &[u8],
+) -> Result<GosValue> {
+    match meta.ptr_depth {
+        0 => {}
+        1 => {
+            return match read::<u8>(buf)? {
+                0 => Ok(FfiCtx::new_nil(ValueType::Pointer)),
+                1 => Ok(FfiCtx::new_pointer(deserialize(
+                    ctx,
+                    enums,
+                    &meta.unptr_to(),
+                    buf,
+                )?)),
+                _ => Err(DecodeError::InvalidValue.into()),
+            };
+        }
+        _ => return Err(unsupported()),
+    }
+    let metas = &ctx.vm_objs.metas;
+    let val = match &metas[meta.key] {
+        MetadataType::Bool => match read::<u8>(buf)? {
+            0 => false.into(),
+            1 => true.into(),
+            _ => return Err(DecodeError::InvalidBool.into()),
+        },
+        MetadataType::Int => (read::<i64>(buf)? as isize).into(),
+        MetadataType::Int8 => read::<i8>(buf)?.into(),
+        MetadataType::Int16 => read::<i16>(buf)?.into(),
+        MetadataType::Int32 => read::<i32>(buf)?.into(),
+        MetadataType::Int64 => read::<i64>(buf)?.into(),
+        MetadataType::Uint => (read::<u64>(buf)? as usize).into(),
+        MetadataType::Uint8 => read::<u8>(buf)?.into(),
+        MetadataType::Uint16 => read::<u16>(buf)?.into(),
+        MetadataType::Uint32 => read::<u32>(buf)?.into(),
+        MetadataType::Uint64 => read::<u64>(buf)?.into(),
+        MetadataType::Float32 => read::<f32>(buf)?.into(),
+        MetadataType::Float64 => read::<f64>(buf)?.into(),
+        MetadataType::Str => {
+            let len = read_len(buf, 1)?;
+            let (bytes, rest) = buf.split_at(len);
+            *buf = rest;
+            let s = std::str::from_utf8(bytes).map_err(|_| DecodeError::InvalidValue)?;
+            FfiCtx::new_string(s)
+        }
+        MetadataType::Array(elem, len) => {
+            let vals = (0..*len)
+                .map(|_| deserialize(ctx, enums, elem, buf))
+                .collect::<Result<Vec<GosValue>>>()?;
+            ctx.new_array(vals, elem.value_type(metas))
+        }
+        MetadataType::Slice(elem) => {
+            let len = read_len(buf, min_size(metas, enums, elem))?;
+            let vals = (0..len)
+                .map(|_| deserialize(ctx, enums, elem, buf))
+                .collect::<Result<Vec<GosValue>>>()?;
+            new_slice(ctx, vals, elem.value_type(metas))?
+        }
+        MetadataType::Map(kmeta, vmeta) => {
+            let entry_size = min_size(metas, enums, kmeta) + min_size(metas, enums, vmeta);
+            let len = read_len(buf, entry_size)?;
+            let mut map = Map::new();
+            let mut last: Option<GosValue> = None;
+            for _ in 0..len {
+                let k = deserialize(ctx, enums, kmeta, buf)?;
+                let v = deserialize(ctx, enums, vmeta, buf)?;
+                // Only accept the canonical form, in which the keys are strictly increasing
+                if last.as_ref().is_some_and(|x| *x >= k) {
+                    return Err(DecodeError::InvalidValue.into());
+                }
+                last = Some(k.clone());
+                map.insert(k, v);
+            }
+            ctx.new_map(map)
+        }
+        MetadataType::Struct(fields) => {
+            let vals = fields
+                .infos()
+                .iter()
+                .map(|f| deserialize(ctx, enums, &f.meta, buf))
+                .collect::<Result<Vec<GosValue>>>()?;
+            ctx.new_struct(vals)
+        }
+        MetadataType::Named(_, inner) => match enums.iter().find(|x| x.meta.key == meta.key) {
+            Some(enum_meta) => deserialize_enum(ctx, enums, enum_meta, buf)?,
+            None => deserialize(ctx, enums, inner, buf)?,
+        },
+        _ => return Err(unsupported()),
+    };
+    Ok(val)
+}
+
+/// Reads a fixed size primitive, failing with `DecodeError::Truncated` if the buffer is too short
+fn read<T: BorshDeserialize>(buf: &mut &[u8]) -> Result<T> {
+    if buf.len() < std::mem::size_of::<T>() {
+        return Err(DecodeError::Truncated.into());
+    }
+    T::deserialize(buf)
+}
+
+/// Reads the length of a string, slice or map, whose elements take at least `elem_size` bytes
+fn read_len(buf: &mut &[u8], elem_size: usize) -> Result<usize> {
+    let len = read::<u32>(buf)? as usize;
+    if len.saturating_mul(elem_size) > buf.len() {
+        return Err(DecodeError::LengthOverflow.into());
+    }
+    Ok(len)
+}
+
+/// The minimum serialized size of the type, used to reject lengths that can't be right
+fn min_size(metas: &MetadataObjs, enums: &[EnumMeta], meta: &Meta) -> usize {
+    if meta.ptr_depth > 0 {
+        return 1;
+    }
+    match &metas[meta.key] {
+        MetadataType::Str | MetadataType::Slice(_) | MetadataType::Map(_, _) => 4,
+        MetadataType::Array(elem, len) => min_size(metas, enums, elem).saturating_mul(*len),
+        MetadataType::Struct(fields) => fields
+            .infos()
+            .iter()
+            .map(|f| min_size(metas, enums, &f.meta))
+            .sum(),
+        MetadataType::Named(_, inner) => match enums.iter().any(|x| x.meta.key == meta.key) {
+            true => 1,
+            false => min_size(metas, enums, inner),
+        },
+        _ => max_size(metas, enums, meta, &[]).unwrap_or(0),
+    }
+}
+
+/// The maximum serialized size of the type, `None` if it's unbounded.
+/// The size of a string, slice or map is bounded by the `max_len` tag of the struct field
+/// that contains it, e.g. `max_len:"10"`. For nested ones, e.g. `[]string`, a list of the
+/// max lengths from the outermost to the innermost can be given: `max_len:"10, 32"`.
+pub fn max_size(
+    metas: &MetadataObjs,
+    enums: &[EnumMeta],
+    meta: &Meta,
+    max_len: &[usize],
+) -> Option<usize> {
+    match meta.ptr_depth {
+        0 => {}
+        1 => return max_size(metas, enums, &meta.unptr_to(), max_len).map(|x| x + 1),
+        _ => return None,
+    }
+    let (len, inner_max_len) = match max_len.split_first() {
+        Some((len, rest)) => (Some(*len), rest),
+        None => (None, max_len),
+    };
+    match &metas[meta.key] {
+        MetadataType::Bool | MetadataType::Int8 | MetadataType::Uint8 => Some(1),
+        MetadataType::Int16 | MetadataType::Uint16 => Some(2),
+        MetadataType::Int32 | MetadataType::Uint32 | MetadataType::Float32 => Some(4),
+        MetadataType::Int
+        | MetadataType::Int64
+        | MetadataType::Uint
+        | MetadataType::Uint64
+        | MetadataType::Float64 => Some(8),
+        MetadataType::Str => Some(4 + len?),
+        MetadataType::Array(elem, size) => {
+            Some(max_size(metas, enums, elem, max_len)?.checked_mul(*size)?)
+        }
+        MetadataType::Slice(elem) => {
+            let elem_size = max_size(metas, enums, elem, inner_max_len)?;
+            Some(4 + elem_size.checked_mul(len?)?)
+        }
+        MetadataType::Map(kmeta, vmeta) => {
+            let entry_size = max_size(metas, enums, kmeta, inner_max_len)?
+                + max_size(metas, enums, vmeta, inner_max_len)?;
+            Some(4 + entry_size.checked_mul(len?)?)
+        }
+        MetadataType::Struct(fields) => fields.infos().iter().try_fold(0usize, |acc, f| {
+            let max_len = f.lookup_tag("max_len").map(|x| parse_max_len(&x));
+            let size = match &max_len {
+                Some(l) => max_size(metas, enums, &f.meta, l.as_ref()?),
+                None => max_size(metas, enums, &f.meta, &[]),
+            };
+            acc.checked_add(size?)
+        }),
+        MetadataType::Named(_, inner) => match enums.iter().find(|x| x.meta.key == meta.key) {
+            Some(enum_meta) => enum_meta
+                .variants
+                .iter()
+                .map(|(_, m)| max_size(metas, enums, m, max_len))
+                .try_fold(0usize, |acc, size| Some(acc.max(size?)))
+                .map(|x| x + 1),
+            None => max_size(metas, enums, inner, max_len),
+        },
+        _ => None,
+    }
+}
+
+/// The serialized size of a type with a fixed layout, i.e. without strings, slices, maps,
+/// pointers or enums. The fields of such a type are always at the same offsets, so that
+/// they can be accessed in place, see `field_location`.
+pub fn fixed_size(metas: &MetadataObjs, meta: &Meta) -> Option<usize> {
+    if meta.ptr_depth > 0 {
+        return None;
+    }
+    match &metas[meta.key] {
+        MetadataType::Str | MetadataType::Slice(_) | MetadataType::Map(_, _) => None,
+        MetadataType::Array(elem, size) => fixed_size(metas, elem)?.checked_mul(*size),
+        MetadataType::Struct(fields) => fields.infos().iter().try_fold(0usize, |acc, f| {
+            acc.checked_add(fixed_size(metas, &f.meta)?)
+        }),
+        MetadataType::Named(_, inner) => fixed_size(metas, inner),
+        _ => max_size(metas, &[], meta, &[]),
+    }
+}
+
+/// The offsets of the fields of a fixed layout struct, `None` if the layout is not fixed
+pub fn field_offsets(metas: &MetadataObjs, meta: &Meta) -> Option<Vec<u64>> {
+    let mut offset = 0;
+    struct_fields(metas, meta)?
+        .infos()
+        .iter()
+        .map(|f| {
+            let field_offset = offset;
+            offset += fixed_size(metas, &f.meta)? as u64;
+            Some(field_offset)
+        })
+        .collect()
+}
+
+/// Where the field at `path` is in the serialized data of a fixed layout struct, and the
+/// type of the field. The path is like `bids[3].price`, and `offsets` are the offsets of
+/// the top level fields, see `field_offsets`.
+pub fn field_location(
+    metas: &MetadataObjs,
+    meta: &Meta,
+    offsets: &[u64],
+    path: &str,
+) -> Option<(usize, Meta)> {
+    let mut offset = 0usize;
+    let mut meta = *meta;
+    for (depth, part) in path.split('.').enumerate() {
+        let (name, mut indices) = part.split_at(part.find('[').unwrap_or(part.len()));
+        let fields = struct_fields(metas, &meta)?.infos();
+        let index = fields.iter().position(|f| f.name == name)?;
+        offset += match depth {
+            0 => *offsets.get(index)? as usize,
+            _ => fields[..index].iter().try_fold(0usize, |acc, f| {
+                acc.checked_add(fixed_size(metas, &f.meta)?)
+            })?,
+        };
+        meta = fields[index].meta;
+        while !indices.is_empty() {
+            let (i, rest) = indices.strip_prefix('[')?.split_once(']')?;
+            let i: usize = i.trim().parse().ok()?;
+            let elem = match underlying(metas, &meta) {
+                MetadataType::Array(elem, len) if meta.ptr_depth == 0 && i < *len => *elem,
+                _ => return None,
+            };
+            offset += i.checked_mul(fixed_size(metas, &elem)?)?;
+            meta = elem;
+            indices = rest;
+        }
+    }
+    fixed_size(metas, &meta)?;
+    Some((offset, meta))
+}
+
+fn struct_fields<'a>(metas: &'a MetadataObjs, meta: &Meta) -> Option<&'a Fields> {
+    match underlying(metas, meta) {
+        MetadataType::Struct(fields) if meta.ptr_depth == 0 => Some(fields),
+        _ => None,
+    }
+}
+
+fn underlying<'a>(metas: &'a MetadataObjs, meta: &Meta) -> &'a MetadataType {
+    match &metas[meta.key] {
+        MetadataType::Named(_, inner) => underlying(metas, inner),
+        t => t,
+    }
+}
+
+fn parse_max_len(tag: &str) -> Option<Vec<usize>> {
+    tag.split(',').map(|x| x.trim().parse().ok()).collect()
+}
+
+/// An enum is serialized as the index of the variant followed by the fields of the variant
+fn serialize_enum<W: Write>(
+    ctx: &FfiCtx,
+    enums: &[EnumMeta],
+    val: &GosValue,
+    enum_meta: &EnumMeta,
+    writer: &mut W,
+) -> Result<()> {
+    let (underlying, meta) = match val.as_interface() {
+        Some(InterfaceObj::Gos(v, Some((m, _)))) => (v, m),
+        _ => return Err(Error::new(ErrorKind::InvalidData, "enum value is nil")),
+    };
+    // The interface may hold a pointer to the variant as well
+    let (underlying, meta) = match meta.ptr_depth {
+        0 => (underlying.clone(), *meta),
+        _ => (
+            ctx.deref_pointer(underlying)
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e.as_str()))?,
+            meta.unptr_to(),
+        ),
+    };
+    let index = enum_meta
+        .variants
+        .iter()
+        .position(|(_, m)| *m == meta)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "not a variant of the enum"))?;
+    (index as u8).serialize(writer)?;
+    serialize(ctx, enums, &underlying, &meta, writer)
+}
+
+fn deserialize_enum(
+    ctx: &FfiCtx,
+    enums: &[EnumMeta],
+    enum_meta: &EnumMeta,
+    buf: &mut &[u8],
+) -> Result<GosValue> {
+    let index = read::<u8>(buf)? as usize;
+    let (_, meta) = enum_meta
+        .variants
+        .get(index)
+        .ok_or(DecodeError::InvalidValue)?;
+    let val = deserialize(ctx, enums, meta, buf)?;
+    Ok(ctx.new_interface(val, Some((&enum_meta.meta, *meta))))
+}
+
+/// Convert a Go identifier into PascalCase the same way Anchor's clients do it.
+fn pascal_case(name: &str) -> String {
+    let mut result = String::with_capacity(name.len());
+    let mut upper = true;
+    for c in name.chars() {
+        if c == '_' {
+            upper = true;
+        } else if upper {
+            result.extend(c.to_uppercase());
+            upper = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Which container the VM uses to store the elements of an array or a slice.
+macro_rules! with_elem_type {
+    ($t:expr, $elem:ident, $body:expr) => {
+        match $t {
+            ValueType::Bool | ValueType::Int8 | ValueType::Uint8 => {
+                type $elem = Elem8;
+                $body
+            }
+            ValueType::Int16 | ValueType::Uint16 => {
+                type $elem = Elem16;
+                $body
+            }
+            ValueType::Int32 | ValueType::Uint32 | ValueType::Float32 => {
+                type $elem = Elem32;
+                $body
+            }
+            ValueType::Int64 | ValueType::Uint64 | ValueType::Float64 | ValueType::Complex64 => {
+                type $elem = Elem64;
+                $body
+            }
+            ValueType::Int | ValueType::Uint | ValueType::UintPtr => {
+                type $elem = ElemWord;
+                $body
+            }
+            _ => {
+                type $elem = GosElem;
+                $body
+            }
+        }
+    };
+}
+
+fn elements(ctx: &FfiCtx, val: &GosValue, elem: &Meta) -> Vec<GosValue> {
+    let t = elem.value_type(&ctx.vm_objs.metas);
+    with_elem_type!(t, E, {
+        match val.typ() {
+            ValueType::Array => val
+                .as_array::<E>()
+                .0
+                .as_rust_slice()
+                .iter()
+                .map(|x| x.clone().into_value(t))
+                .collect(),
+            _ => match val.as_slice::<E>() {
+                Some(s) => {
+                    s.0.as_rust_slice()
+                        .iter()
+                        .map(|x| x.clone().into_value(t))
+                        .collect()
+                }
+                None => vec![],
+            },
+        }
+    })
+}
+
+fn new_slice(ctx: &FfiCtx, vals: Vec<GosValue>, t: ValueType) -> Result<GosValue> {
+    let array = ctx.new_array(vals, t);
+    with_elem_type!(t, E, {
+        SliceObj::<E>::with_array(array, 0, -1)
+            .map(|s| GosValue::new_slice(s, t))
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.as_str()))
+    })
+}
+
+fn unsupported() -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        "type not supported by the data codec",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use go_vm::CodeGenVMCtx;
+
+    fn field(name: &str, meta: Meta) -> FieldInfo {
+        FieldInfo {
+            meta,
+            name: name.to_owned(),
+            tag: None,
+            embedded_indices: None,
+        }
+    }
+
+    #[test]
+    fn discriminator_matches_anchor() {
+        // sha256("account:EscrowAccountData")[..8], what Anchor generates for the same name
+        let expected = solana_program::hash::hash(b"account:EscrowAccountData").to_bytes();
+        assert_eq!(account_discriminator("EscrowAccountData"), expected[..8]);
+        assert_eq!(account_discriminator("escrow_account_data"), expected[..8]);
+    }
+
+    #[test]
+    fn version_in_discriminator() {
+        assert_eq!(versioned_name("Counter", 0), "Counter");
+        assert_eq!(versioned_name("Counter", 1), "CounterV1");
+        let v1 = solana_program::hash::hash(b"account:CounterV1").to_bytes();
+        assert_eq!(
+            account_discriminator(&versioned_name("Counter", 1)),
+            v1[..8]
+        );
+        assert_ne!(
+            account_discriminator(&versioned_name("Counter", 1)),
+            account_discriminator(&versioned_name("Counter", 0))
+        );
+    }
+
+    #[test]
+    fn max_size_with_tags() {
+        let mut vm_ctx = CodeGenVMCtx::new(VMObjects::new());
+        let prim = vm_ctx.prim_meta();
+        let (m_u64, m_str) = (prim.muint64, prim.mstr);
+        let m_names = Meta::new_slice(m_str, vm_ctx.metas_mut());
+        let mut names = field("names", m_names);
+        names.tag = Some(r#"max_len:"10, 32""#.to_owned());
+        let meta =
+            vm_ctx.new_struct_meta(Fields::new(vec![field("amount", m_u64.ptr_to()), names]));
+        let metas = &vm_ctx.ffi_ctx().vm_objs.metas;
+        assert_eq!(
+            max_size(metas, &[], &meta, &[]),
+            Some(1 + 8 + 4 + 10 * (4 + 32))
+        );
+        assert_eq!(max_size(metas, &[], &m_names, &[10]), None);
+    }
+
+    #[test]
+    fn fixed_layout_fields() {
+        let mut vm_ctx = CodeGenVMCtx::new(VMObjects::new());
+        let prim = vm_ctx.prim_meta();
+        let (m_u64, m_u8, m_str) = (prim.muint64, prim.muint8, prim.mstr);
+        let m_order = vm_ctx.new_struct_meta(Fields::new(vec![
+            field("price", m_u64),
+            field("side", m_u8),
+        ]));
+        let m_orders = Meta::new_array(m_order, 4, vm_ctx.metas_mut());
+        let meta = vm_ctx.new_struct_meta(Fields::new(vec![
+            field("count", m_u8),
+            field("bids", m_orders),
+        ]));
+        let unbounded = vm_ctx.new_struct_meta(Fields::new(vec![field("name", m_str)]));
+        let metas = &vm_ctx.ffi_ctx().vm_objs.metas;
+
+        assert_eq!(fixed_size(metas, &meta), Some(1 + 4 * 9));
+        assert_eq!(fixed_size(metas, &unbounded), None);
+        let offsets = field_offsets(metas, &meta).unwrap();
+        assert_eq!(offsets, vec![0, 1]);
+        let (offset, field_meta) = field_location(metas, &meta, &offsets, "bids[2].side").unwrap();
+        assert_eq!((offset, field_meta.key), (1 + 2 * 9 + 8, m_u8.key));
+        assert_eq!(field_location(metas, &meta, &offsets, "bids").unwrap().0, 1);
+        assert!(field_location(metas, &meta, &offsets, "bids[4].side").is_none());
+        assert!(field_location(metas, &meta, &offsets, "count.price").is_none());
+        assert!(field_location(metas, &meta, &offsets, "asks").is_none());
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut vm_ctx = CodeGenVMCtx::new(VMObjects::new());
+        let prim = vm_ctx.prim_meta();
+        let (m_u64, m_u8, m_u16, m_str, m_bool) = (
+            prim.muint64,
+            prim.muint8,
+            prim.muint16,
+            prim.mstr,
+            prim.mbool,
+        );
+        let m_key = Meta::new_array(m_u8, 32, vm_ctx.metas_mut());
+        let m_list = Meta::new_slice(m_u16, vm_ctx.metas_mut());
+        let m_map = Meta::new_map(m_str, m_u64, vm_ctx.metas_mut());
+        let meta = vm_ctx.new_struct_meta(Fields::new(vec![
+            field("amount", m_u64),
+            field("key", m_key),
+            field("name", m_str),
+            field("list", m_list),
+            field("flag", m_bool),
+            field("some", m_u64.ptr_to()),
+            field("none", m_key.ptr_to()),
+            field("map", m_map),
+        ]));
+
+        let mut data = vec![];
+        7u64.serialize(&mut data).unwrap();
+        [3u8; 32].serialize(&mut data).unwrap();
+        "golana".serialize(&mut data).unwrap();
+        vec![1u16, 2, 3].serialize(&mut data).unwrap();
+        true.serialize(&mut data).unwrap();
+        Some(9u64).serialize(&mut data).unwrap();
+        None::<[u8; 32]>.serialize(&mut data).unwrap();
+        std::collections::BTreeMap::from([("b".to_owned(), 2u64), ("a".to_owned(), 1u64)])
+            .serialize(&mut data)
+            .unwrap();
+
+        let ctx = vm_ctx.ffi_ctx();
+        let mut buf: &[u8] = &data;
+        let val = deserialize(&ctx, &[], &meta, &mut buf).unwrap();
+        assert!(buf.is_empty());
+        let mut out = vec![];
+        serialize(&ctx, &[], &val, &meta, &mut out).unwrap();
+        assert_eq!(data, out);
+    }
+
+    #[test]
+    fn enum_round_trip() {
+        let mut vm_ctx = CodeGenVMCtx::new(VMObjects::new());
+        let prim = vm_ctx.prim_meta();
+        let (m_u64, m_u8) = (prim.muint64, prim.muint8);
+        let iface = Meta::new_interface(Fields::new(vec![]), vm_ctx.metas_mut());
+        let m_side = Meta::new_named(iface, vm_ctx.metas_mut());
+        let bid = vm_ctx.new_struct_meta(Fields::new(vec![]));
+        let m_bid = Meta::new_named(bid, vm_ctx.metas_mut());
+        let ask = vm_ctx.new_struct_meta(Fields::new(vec![field("limit", m_u64)]));
+        let m_ask = Meta::new_named(ask, vm_ctx.metas_mut());
+        let m_sides = Meta::new_slice(m_side, vm_ctx.metas_mut());
+        let meta = vm_ctx.new_struct_meta(Fields::new(vec![
+            field("side", m_side),
+            field("history", m_sides),
+            field("qty", m_u8),
+        ]));
+        let enums = [EnumMeta {
+            name: "Side".to_owned(),
+            meta: m_side,
+            variants: vec![("Bid".to_owned(), m_bid), ("Ask".to_owned(), m_ask)],
+        }];
+
+        // Ask { limit: 5 }, [Bid, Ask { limit: 7 }], 3
+        let mut data = vec![1u8];
+        5u64.serialize(&mut data).unwrap();
+        2u32.serialize(&mut data).unwrap();
+        data.extend([0u8, 1]);
+        7u64.serialize(&mut data).unwrap();
+        data.push(3);
+
+        let ctx = vm_ctx.ffi_ctx();
+        let mut buf: &[u8] = &data;
+        let val = deserialize(&ctx, &enums, &meta, &mut buf).unwrap();
+        assert!(buf.is_empty());
+        let mut out = vec![];
+        serialize(&ctx, &enums, &val, &meta, &mut out).unwrap();
+        assert_eq!(data, out);
+        assert_eq!(
+            max_size(&ctx.vm_objs.metas, &enums, &m_side, &[]),
+            Some(1 + 8)
+        );
+
+        let mut bad_variant = data.clone();
+        bad_variant[0] = 2;
+        let mut buf: &[u8] = &bad_variant;
+        let err = deserialize(&ctx, &enums, &meta, &mut buf).unwrap_err();
+        assert_eq!(DecodeError::of(&err), DecodeError::InvalidValue);
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let mut vm_ctx = CodeGenVMCtx::new(VMObjects::new());
+        let prim = vm_ctx.prim_meta();
+        let (m_u16, m_str, m_bool) = (prim.muint16, prim.mstr, prim.mbool);
+        let m_list = Meta::new_slice(m_u16, vm_ctx.metas_mut());
+        let meta = vm_ctx.new_struct_meta(Fields::new(vec![
+            field("flag", m_bool),
+            field("name", m_str),
+            field("list", m_list),
+        ]));
+        let ctx = vm_ctx.ffi_ctx();
+        let decode = |data: &[u8]| {
+            let mut buf = data;
+            deserialize(&ctx, &[], &meta, &mut buf).map_err(|e| DecodeError::of(&e))
+        };
+
+        let mut data = vec![];
+        (true, "go", vec![1u16, 2]).serialize(&mut data).unwrap();
+        assert!(decode(&data).is_ok());
+        assert_eq!(decode(&data[..3]).err(), Some(DecodeError::Truncated));
+
+        let mut bad_bool = data.clone();
+        bad_bool[0] = 2;
+        assert_eq!(decode(&bad_bool).err(), Some(DecodeError::InvalidBool));
+
+        // The slice claims 3 elements, but there are only 4 bytes left
+        let mut overlong = data.clone();
+        overlong[7] = 3;
+        assert_eq!(decode(&overlong).err(), Some(DecodeError::LengthOverflow));
+
+        let mut bad_utf8 = data.clone();
+        bad_utf8[5] = 0xff;
+        assert_eq!(decode(&bad_utf8).err(), Some(DecodeError::InvalidValue));
+    }
+}
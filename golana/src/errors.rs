@@ -41,4 +41,78 @@ pub enum GolError {
 
     #[msg("Bad AuthorityType value")]
     BadAuthorityType,
+    #[msg("Account data discriminator doesn't match against the data type")]
+    RtCheckDiscriminator,
+    #[msg("The program was finalized by another version of the loader, deploy it again")]
+    MetaVersionMismatch,
+    #[msg("An interface used as an enum has to have at least one method")]
+    EmptyEnumInterface,
+    #[msg("No struct implements the interface used as an enum")]
+    NoEnumVariant,
+    #[msg("An enum can have at most 256 variants")]
+    TooManyEnumVariants,
+    #[msg("Data type has a string, slice or map without a valid `max_len` tag")]
+    UnboundedDataType,
+    #[msg("Account data space is too small for the data, resize the account first")]
+    AccountDataTooSmall,
+    #[msg("Only the data of accounts owned by the Golana loader can be written")]
+    RtCheckDataOwner,
+    #[msg("The lamports of the account would overflow")]
+    RtCheckLamportsOverflow,
+    #[msg("Type of the data field doesn't match against the data tag of the account")]
+    DataTypeMismatch,
+    #[msg("Data can only be loaded from accounts owned by the Golana loader")]
+    RtCheckDataNotOwned,
+    #[msg("Method 'Validate' has to take no arguments and return an error")]
+    BadValidateSignature,
+    #[msg("Instruction arguments end unexpectedly")]
+    RtCheckArgsTruncated,
+    #[msg("Instruction arguments have unexpected trailing bytes")]
+    RtCheckArgsTrailingBytes,
+    #[msg("Instruction arguments have a bool that is neither 0 nor 1")]
+    RtCheckArgsInvalidBool,
+    #[msg("Instruction arguments have a length longer than the remaining bytes")]
+    RtCheckArgsLengthOverflow,
+    #[msg("Instruction arguments have an invalid value")]
+    RtCheckArgsInvalidValue,
+    #[msg("The account named by `dup=` is not found in the Ix")]
+    DupAccountNotFound,
+    #[msg("An account with a data tag can't be marked with `dup=` or named by it")]
+    DupAccountWithData,
+    #[msg("The same account is passed for two fields, one of them `mut`, without `dup=`")]
+    RtCheckDuplicateAccount,
+    #[msg("Account handle is out of range for the instruction")]
+    RtCheckBadAccountHandle,
+    #[msg("The program to call is not the Golana loader")]
+    NotGolanaLoader,
+    #[msg("The account is not the PDA of the seed and bump")]
+    RtCheckPdaMismatch,
+    #[msg("Lamports can only be taken from accounts owned by the Golana loader")]
+    RtCheckLamportsOwner,
+    #[msg("The account doesn't have enough lamports")]
+    RtCheckInsufficientLamports,
+    #[msg("The account would no longer be rent exempt")]
+    RtCheckNotRentExempt,
+    #[msg("Reading or writing out of the range of the account data")]
+    RtCheckDataOutOfRange,
+    #[msg("A zerocopy data type can't have strings, slices, maps, pointers or enums")]
+    ZeroCopyNotFixedLayout,
+    #[msg("The data of a zerocopy account can't be declared in Ix, use ReadField instead")]
+    ZeroCopyDataDeclare,
+    #[msg("Fields can only be accessed in place for accounts with a zerocopy data tag")]
+    RtCheckNotZeroCopy,
+    #[msg("No field is found at the path in the account data")]
+    RtCheckFieldPath,
+    #[msg("The type of the value doesn't match against the field")]
+    RtCheckFieldType,
+    #[msg("The version in the data tag has to be a number from 0 to 255")]
+    BadDataVersion,
+    #[msg("An old version of a data type has to have a `Migrate() interface{{}}` method")]
+    BadMigrateSignature,
+    #[msg("A zerocopy data type can't have a version, its accounts aren't migrated")]
+    ZeroCopyVersioned,
+    #[msg("Unknown option in the data tag, expected zerocopy or version=N")]
+    BadDataTag,
+    #[msg("Migrate has to return a pointer to the current version of the data type")]
+    RtCheckMigrateType,
 }
@@ -1,5 +1,7 @@
 mod checker;
 
+pub mod codec;
+
 mod errors;
 
 pub use checker::*;
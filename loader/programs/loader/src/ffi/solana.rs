@@ -1,10 +1,15 @@
 use crate::goscript::Instruction;
 use anchor_lang::error;
 use anchor_lang::prelude::SolanaSysvar;
+use anchor_lang::{InstructionData, ToAccountMetas};
 use go_vm::types::*;
 use go_vm::*;
-use golana::GolError;
+use golana::{codec, GolError, OldDataMeta};
+use solana_program::instruction::AccountMeta;
 use solana_program::program_option::COption;
+use solana_program::sysvar::{
+    clock::Clock, epoch_schedule::EpochSchedule, instructions, rent::Rent,
+};
 use solana_program::{self, account_info::AccountInfo, pubkey::Pubkey};
 use std::rc::Rc;
 
@@ -17,7 +22,15 @@ pub struct SolanaFfi;
 #[ffi_impl]
 impl SolanaFfi {
     fn ffi_get_ix(ctx: &FfiCtx) -> GosValue {
-        Self::get_instruction(ctx).get_ix(ctx)
+        Self::get_instruction(ctx).get_ix()
+    }
+
+    fn ffi_prepare_ix(ctx: &FfiCtx) -> bool {
+        Self::get_instruction(ctx).prepare_ix(ctx)
+    }
+
+    fn ffi_get_validator(ctx: &FfiCtx) -> RuntimeResult<GosValue> {
+        Self::get_instruction(ctx).get_validator(ctx)
     }
 
     fn ffi_get_id(ctx: &FfiCtx) -> GosValue {
@@ -34,12 +47,116 @@ impl SolanaFfi {
         solana_program::log::sol_log_compute_units();
     }
 
-    fn ffi_find_program_address(ctx: &FfiCtx, seed: GosValue, program: GosValue) -> (GosValue, u8) {
-        let program_id =
-            Self::get_pub_key(ctx, &program).expect("ffi_find_program_address: bad program id");
-        let hashed = Self::get_seed_hash(seed.as_string().as_str().as_bytes(), &program_id);
+    fn ffi_find_program_address(
+        ctx: &FfiCtx,
+        seed: GosValue,
+        program: GosValue,
+    ) -> RuntimeResult<(u8, GosValue)> {
+        let program_id = Self::get_pub_key(ctx, &program)?;
+        // The seed can be any bytes, see `Seeds` on the Go side
+        let hashed = Self::get_seed_hash(&seed.as_string().as_raw_slice(), &program_id);
         let (pk, bump) = Pubkey::find_program_address(&[&hashed[..]], &crate::ID);
-        (Self::make_pub_key_ptr(ctx, pk), bump)
+        Ok((bump, Self::make_pub_key_ptr(ctx, pk)))
+    }
+
+    fn ffi_create_program_address(
+        ctx: &FfiCtx,
+        seed: GosValue,
+        bump: u8,
+        program: GosValue,
+    ) -> (GosValue, GosValue) {
+        let result: anyhow::Result<GosValue> = (move || {
+            let program_id = Self::get_pub_key(ctx, &program)?;
+            let pk =
+                Self::create_program_address(&seed.as_string().as_raw_slice(), bump, &program_id)?;
+            Ok(Self::make_pub_key_ptr(ctx, pk))
+        })();
+        Self::unwrap_result(result)
+    }
+
+    fn ffi_verify_pda(ctx: &FfiCtx, account_index: usize, seed: GosValue, bump: u8) -> GosValue {
+        let result: anyhow::Result<()> = (move || {
+            let inst = Self::get_instruction(ctx);
+            let account = inst.account(account_index)?;
+            let pk = Self::create_program_address(
+                &seed.as_string().as_raw_slice(),
+                bump,
+                inst.gos_program_id,
+            )?;
+            if *account.key != pk {
+                return Err(error!(GolError::RtCheckPdaMismatch).into());
+            }
+            Ok(())
+        })();
+        Self::unwrap_empty_result(result)
+    }
+
+    fn ffi_clock(ctx: &FfiCtx) -> RuntimeResult<GosValue> {
+        let clock = Clock::get().map_err(|e| RuntimeError::new(e.to_string()))?;
+        Ok(FfiCtx::new_pointer(ctx.new_struct(vec![
+            GosValue::from(clock.slot),
+            GosValue::from(clock.epoch_start_timestamp),
+            GosValue::from(clock.epoch),
+            GosValue::from(clock.leader_schedule_epoch),
+            GosValue::from(clock.unix_timestamp),
+        ])))
+    }
+
+    fn ffi_rent(ctx: &FfiCtx) -> RuntimeResult<GosValue> {
+        let rent = Rent::get().map_err(|e| RuntimeError::new(e.to_string()))?;
+        Ok(FfiCtx::new_pointer(ctx.new_struct(vec![
+            GosValue::from(rent.lamports_per_byte_year),
+            GosValue::from(rent.exemption_threshold),
+            GosValue::from(rent.burn_percent),
+        ])))
+    }
+
+    fn ffi_epoch_schedule(ctx: &FfiCtx) -> RuntimeResult<GosValue> {
+        let schedule = EpochSchedule::get().map_err(|e| RuntimeError::new(e.to_string()))?;
+        Ok(FfiCtx::new_pointer(ctx.new_struct(vec![
+            GosValue::from(schedule.slots_per_epoch),
+            GosValue::from(schedule.leader_schedule_slot_offset),
+            GosValue::from(schedule.warmup),
+            GosValue::from(schedule.first_normal_epoch),
+            GosValue::from(schedule.first_normal_slot),
+        ])))
+    }
+
+    fn ffi_load_current_index(ctx: &FfiCtx, sysvar_index: usize) -> (u16, GosValue) {
+        let result: anyhow::Result<u16> = (move || {
+            let sysvar = Self::get_instruction(ctx).account(sysvar_index)?;
+            Ok(instructions::load_current_index_checked(sysvar)?)
+        })();
+        match result {
+            Ok(index) => (index, FfiCtx::new_nil(ValueType::UnsafePtr)),
+            Err(e) => (0, Self::unwrap_empty_result(Err(e))),
+        }
+    }
+
+    fn ffi_load_instruction_at(
+        ctx: &FfiCtx,
+        sysvar_index: usize,
+        index: u16,
+    ) -> (GosValue, GosValue) {
+        let result: anyhow::Result<GosValue> = (move || {
+            let sysvar = Self::get_instruction(ctx).account(sysvar_index)?;
+            let ix = instructions::load_instruction_at_checked(index as usize, sysvar)?;
+            Self::make_tx_instruction(ctx, ix)
+        })();
+        Self::unwrap_result(result)
+    }
+
+    fn ffi_get_instruction_relative(
+        ctx: &FfiCtx,
+        sysvar_index: usize,
+        offset: i64,
+    ) -> (GosValue, GosValue) {
+        let result: anyhow::Result<GosValue> = (move || {
+            let sysvar = Self::get_instruction(ctx).account(sysvar_index)?;
+            let ix = instructions::get_instruction_relative(offset, sysvar)?;
+            Self::make_tx_instruction(ctx, ix)
+        })();
+        Self::unwrap_result(result)
     }
 
     fn ffi_account_create(
@@ -49,114 +166,564 @@ impl SolanaFfi {
         space: u64,
         signer_seeds: GosValue,
     ) -> GosValue {
-        let inst = Self::get_instruction(ctx);
-        let from = inst.accounts[from_index].clone();
-        let to = inst.accounts[to_index].clone();
+        let result =
+            Self::create_account(ctx, from_index, to_index, space, &crate::ID, signer_seeds);
+        Self::unwrap_empty_result(result)
+    }
+
+    fn ffi_account_create_with_owner(
+        ctx: &FfiCtx,
+        from_index: usize,
+        to_index: usize,
+        space: u64,
+        owner: GosValue,
+        signer_seeds: GosValue,
+    ) -> GosValue {
+        let result: anyhow::Result<()> = (move || {
+            let owner = Self::get_pub_key(ctx, &owner)?;
+            Self::create_account(ctx, from_index, to_index, space, &owner, signer_seeds)
+        })();
+        Self::unwrap_empty_result(result)
+    }
+
+    fn ffi_account_create_with_seed(
+        ctx: &FfiCtx,
+        from_index: usize,
+        to_index: usize,
+        base_index: usize,
+        seed: GosValue,
+        space: u64,
+        owner: GosValue,
+        signer_seeds: GosValue,
+    ) -> GosValue {
         let result: anyhow::Result<()> = (move || {
-            let sol_rent = anchor_lang::prelude::Rent::get()?;
-            let lamports = sol_rent.minimum_balance(space as usize);
-            let ix = solana_program::system_instruction::create_account(
+            let inst = Self::get_instruction(ctx);
+            let from = inst.account(from_index)?.clone();
+            let to = inst.account(to_index)?.clone();
+            let base = inst.account(base_index)?.clone();
+            let owner = Self::get_pub_key(ctx, &owner)?;
+            let lamports = Rent::get()?.minimum_balance(space as usize);
+            let ix = solana_program::system_instruction::create_account_with_seed(
                 from.key,
                 to.key,
+                base.key,
+                &seed.as_string().as_str(),
                 lamports,
                 space,
-                &crate::ID,
+                &owner,
             );
-            Self::invoke_signed(&ix, &[from, to], signer_seeds, inst.gos_program_id)
+            Self::invoke_signed(ctx, &ix, &[from, to, base], signer_seeds)
         })();
         Self::unwrap_empty_result(result)
     }
 
-    fn ffi_account_key(ctx: &FfiCtx, index: usize) -> GosValue {
-        let inst = Self::get_instruction(ctx);
-        Self::make_pub_key_ptr(ctx, *inst.accounts[index].key)
+    fn ffi_system_transfer(
+        ctx: &FfiCtx,
+        from_index: usize,
+        to_index: usize,
+        lamports: u64,
+        signer_seeds: GosValue,
+    ) -> GosValue {
+        let result: anyhow::Result<()> = (move || {
+            let inst = Self::get_instruction(ctx);
+            let from = inst.account(from_index)?.clone();
+            let to = inst.account(to_index)?.clone();
+            let ix = solana_program::system_instruction::transfer(from.key, to.key, lamports);
+            Self::invoke_signed(ctx, &ix, &[from, to], signer_seeds)
+        })();
+        Self::unwrap_empty_result(result)
     }
 
-    fn ffi_account_lamports(ctx: &FfiCtx, index: usize) -> u64 {
-        let inst = Self::get_instruction(ctx);
-        **inst.accounts[index].lamports.borrow()
+    fn ffi_account_allocate(
+        ctx: &FfiCtx,
+        index: usize,
+        space: u64,
+        signer_seeds: GosValue,
+    ) -> GosValue {
+        let result: anyhow::Result<()> = (move || {
+            let inst = Self::get_instruction(ctx);
+            let account = inst.account(index)?.clone();
+            let ix = solana_program::system_instruction::allocate(account.key, space);
+            Self::invoke_signed(ctx, &ix, &[account], signer_seeds)
+        })();
+        Self::unwrap_empty_result(result)
     }
 
-    fn ffi_set_account_lamports(ctx: &FfiCtx, index: usize, lamports: u64) {
-        let inst = Self::get_instruction(ctx);
-        **inst.accounts[index].lamports.borrow_mut() = lamports;
+    fn ffi_account_assign(
+        ctx: &FfiCtx,
+        index: usize,
+        owner: GosValue,
+        signer_seeds: GosValue,
+    ) -> GosValue {
+        let result: anyhow::Result<()> = (move || {
+            let inst = Self::get_instruction(ctx);
+            let account = inst.account(index)?.clone();
+            let owner = Self::get_pub_key(ctx, &owner)?;
+            let ix = solana_program::system_instruction::assign(account.key, &owner);
+            Self::invoke_signed(ctx, &ix, &[account], signer_seeds)
+        })();
+        Self::unwrap_empty_result(result)
+    }
+
+    fn ffi_transfer_lamports(
+        ctx: &FfiCtx,
+        from_index: usize,
+        to_index: usize,
+        amount: u64,
+    ) -> GosValue {
+        let result: anyhow::Result<()> = (move || {
+            let inst = Self::get_instruction(ctx);
+            let from = inst.account(from_index)?;
+            let to = inst.account(to_index)?;
+            if *from.owner != crate::ID {
+                return Err(error!(GolError::RtCheckLamportsOwner).into());
+            }
+            if !from.is_writable || !to.is_writable {
+                return Err(error!(GolError::RtCheckMutable).into());
+            }
+            let remainder = from
+                .lamports()
+                .checked_sub(amount)
+                .ok_or(error!(GolError::RtCheckInsufficientLamports))?;
+            // Taking all the lamports is how an account gets closed
+            if remainder > 0 && !Rent::get()?.is_exempt(remainder, from.data_len()) {
+                return Err(error!(GolError::RtCheckNotRentExempt).into());
+            }
+            if from.key == to.key {
+                return Ok(());
+            }
+            let to_lamports = to
+                .lamports()
+                .checked_add(amount)
+                .ok_or(error!(GolError::RtCheckLamportsOverflow))?;
+            **from.lamports.borrow_mut() = remainder;
+            **to.lamports.borrow_mut() = to_lamports;
+            Ok(())
+        })();
+        Self::unwrap_empty_result(result)
+    }
+
+    fn ffi_invoke(
+        ctx: &FfiCtx,
+        program_index: usize,
+        metas: GosValue,
+        data: GosValue,
+        signer_seeds: GosValue,
+    ) -> GosValue {
+        let result: anyhow::Result<()> = (move || {
+            let inst = Self::get_instruction(ctx);
+            let program = inst.account(program_index)?;
+            let (account_metas, mut account_infos) = Self::get_account_metas(ctx, &metas)?;
+            account_infos.push(program.clone());
+            let ix = solana_program::instruction::Instruction::new_with_bytes(
+                *program.key,
+                &Self::get_bytes(&data)?,
+                account_metas,
+            );
+            Self::invoke_signed(ctx, &ix, &account_infos, signer_seeds)
+        })();
+        Self::unwrap_empty_result(result)
+    }
+
+    fn ffi_invoke_golana(
+        ctx: &FfiCtx,
+        loader_index: usize,
+        mem_dump_index: usize,
+        id: GosValue,
+        metas: GosValue,
+        args: GosValue,
+        signer_seeds: GosValue,
+    ) -> GosValue {
+        let result: anyhow::Result<()> = (move || {
+            let inst = Self::get_instruction(ctx);
+            let loader = inst.account(loader_index)?;
+            let mem_dump = inst.account(mem_dump_index)?;
+            if *loader.key != crate::ID {
+                return Err(error!(GolError::NotGolanaLoader).into());
+            }
+            // The callee runs on the same loader, so it's a `gol_execute` with the mem_dump
+            // of the callee followed by the accounts of its Ix
+            let mut account_metas = crate::accounts::GolExecute {
+                mem_dump: *mem_dump.key,
+            }
+            .to_account_metas(None);
+            let mut account_infos = vec![mem_dump.clone()];
+            let (metas, infos) = Self::get_account_metas(ctx, &metas)?;
+            account_metas.extend(metas);
+            account_infos.extend(infos);
+            account_infos.push(loader.clone());
+            let data = crate::instruction::GolExecute {
+                id: id.as_string().as_str().to_owned(),
+                args: Self::get_bytes(&args)?,
+            }
+            .data();
+            let ix = solana_program::instruction::Instruction::new_with_bytes(
+                crate::ID,
+                &data,
+                account_metas,
+            );
+            Self::invoke_signed(ctx, &ix, &account_infos, signer_seeds)
+        })();
+        Self::unwrap_empty_result(result)
+    }
+
+    fn ffi_anchor_sighash(ctx: &FfiCtx, name: GosValue) -> GosValue {
+        let preimage = format!("global:{}", name.as_string().as_str());
+        let hash = solana_program::hash::hash(preimage.as_bytes()).to_bytes();
+        ctx.new_primitive_array(hash[..8].to_vec(), ValueType::Uint8)
+    }
+
+    fn ffi_serialize(ctx: &FfiCtx, data: GosValue) -> (GosValue, GosValue) {
+        let result = || -> anyhow::Result<GosValue> {
+            let (val, meta) = match data.as_non_nil_interface()? {
+                InterfaceObj::Gos(val, Some((meta, _))) if meta.ptr_depth <= 1 => (val, *meta),
+                _ => return Err(anyhow::anyhow!("Serialize: bad data type")),
+            };
+            let (val, meta) = match meta.ptr_depth {
+                0 => (val.clone(), meta),
+                _ => (ctx.deref_pointer(val)?, meta.unptr_to()),
+            };
+            let inst = Self::get_instruction(ctx);
+            let mut buf = vec![];
+            codec::serialize(ctx, &inst.tx_meta.enums, &val, &meta, &mut buf)?;
+            Ok(Self::make_byte_slice(ctx, buf)?)
+        }();
+        Self::unwrap_result(result)
+    }
+
+    fn ffi_account_key(ctx: &FfiCtx, index: usize) -> RuntimeResult<GosValue> {
+        let account = Self::get_account(ctx, index)?;
+        Ok(Self::make_pub_key_ptr(ctx, *account.key))
+    }
+
+    fn ffi_account_lamports(ctx: &FfiCtx, index: usize) -> RuntimeResult<u64> {
+        let account = Self::get_account(ctx, index)?;
+        Ok(**account.lamports.borrow())
+    }
+
+    fn ffi_set_account_lamports(ctx: &FfiCtx, index: usize, lamports: u64) -> RuntimeResult<()> {
+        let account = Self::get_account(ctx, index)?;
+        **account.lamports.borrow_mut() = lamports;
+        Ok(())
+    }
+
+    fn ffi_account_owner(ctx: &FfiCtx, index: usize) -> RuntimeResult<GosValue> {
+        let account = Self::get_account(ctx, index)?;
+        Ok(Self::make_pub_key_ptr(ctx, *account.owner))
+    }
+
+    fn ffi_account_executable(ctx: &FfiCtx, index: usize) -> RuntimeResult<bool> {
+        let account = Self::get_account(ctx, index)?;
+        Ok(account.executable)
+    }
+
+    fn ffi_account_rent_epoch(ctx: &FfiCtx, index: usize) -> RuntimeResult<u64> {
+        let account = Self::get_account(ctx, index)?;
+        Ok(account.rent_epoch)
+    }
+
+    fn ffi_account_data(ctx: &FfiCtx, index: usize) -> RuntimeResult<GosValue> {
+        Self::load_account_data(ctx, index).map_err(|e| RuntimeError::new(e.to_string()))
+    }
+
+    fn ffi_account_save_data(ctx: &FfiCtx, index: usize, data_iface: GosValue) -> GosValue {
+        let result = || -> anyhow::Result<()> {
+            let data_ptr = data_iface
+                .as_non_nil_interface()?
+                .underlying_value()
+                .ok_or(anyhow::anyhow!("SaveData: bad data"))?;
+            let data_obj = ctx.deref_pointer(data_ptr)?;
+            Self::write_account_data(ctx, index, &data_obj)?;
+            // So that the following Data() calls return what's saved
+            Self::get_instruction(ctx).data_cache.borrow_mut()[index] = Some(data_iface.clone());
+            Ok(())
+        }();
+        Self::unwrap_empty_result(result)
     }
 
-    fn ffi_account_owner(ctx: &FfiCtx, index: usize) -> GosValue {
+    fn ffi_account_data_as(ctx: &FfiCtx, index: usize, typ: GosValue) -> (GosValue, GosValue) {
+        let result = || -> anyhow::Result<GosValue> {
+            let meta = match typ.as_non_nil_interface()? {
+                InterfaceObj::Gos(_, Some((meta, _))) if meta.ptr_depth == 1 => meta.unptr_to(),
+                _ => return Err(anyhow::anyhow!("DataAs: bad data type")),
+            };
+            let inst = Self::get_instruction(ctx);
+            // A type used in a data tag with a version is stored under the versioned name,
+            // as are its old versions, any other type under its own name
+            let data_metas = inst
+                .tx_meta
+                .instructions
+                .iter()
+                .flat_map(|ix| ix.accounts.iter())
+                .filter_map(|x| x.data_meta.as_ref());
+            let stored = data_metas
+                .flat_map(|x| {
+                    let current = std::iter::once((x.meta, x.discriminator));
+                    current.chain(
+                        x.old_versions
+                            .iter()
+                            .map(|old| (old.meta, old.discriminator)),
+                    )
+                })
+                .find(|(m, _)| m.key == meta.key);
+            let discriminator = match stored {
+                Some((_, discriminator)) => discriminator,
+                None => codec::account_discriminator(
+                    &golana::type_name(&meta, ctx.vm_objs)
+                        .ok_or(error!(GolError::DataTypeNotFound))?,
+                ),
+            };
+            // What Data() has loaded from the account is saved first, so the result sees it
+            Self::commit_account_data(ctx, index)?;
+            let val = Self::decode_data(ctx, index, &discriminator, &meta)?;
+            Ok(ctx.new_empty_interface(FfiCtx::new_pointer(val), meta.ptr_to()))
+        };
+        Self::unwrap_result(result())
+    }
+
+    fn ffi_account_read_field(ctx: &FfiCtx, index: usize, path: GosValue) -> (GosValue, GosValue) {
+        let result = || -> anyhow::Result<GosValue> {
+            let (range, meta) = Self::locate_field(ctx, index, &path.as_string().as_str())?;
+            let account = Self::get_instruction(ctx).account(index)?;
+            let mut buf: &[u8] = &account.data.borrow()[range];
+            let val = codec::deserialize(ctx, &[], &meta, &mut buf)?;
+            Ok(ctx.new_empty_interface(val, meta))
+        };
+        Self::unwrap_result(result())
+    }
+
+    fn ffi_account_write_field(
+        ctx: &FfiCtx,
+        index: usize,
+        path: GosValue,
+        value: GosValue,
+    ) -> GosValue {
+        let result = || -> anyhow::Result<()> {
+            let (range, meta) = Self::locate_field(ctx, index, &path.as_string().as_str())?;
+            let val = match value.as_non_nil_interface()? {
+                InterfaceObj::Gos(val, Some((val_meta, _)))
+                    if val_meta.key == meta.key && val_meta.ptr_depth == meta.ptr_depth =>
+                {
+                    val.clone()
+                }
+                _ => return Err(error!(GolError::RtCheckFieldType).into()),
+            };
+            let inst = Self::get_instruction(ctx);
+            let account = inst.account(index)?;
+            if *account.owner != crate::ID {
+                return Err(error!(GolError::RtCheckDataOwner).into());
+            }
+            if !account.is_writable {
+                return Err(error!(GolError::RtCheckMutable).into());
+            }
+            let mut buf = vec![];
+            codec::serialize(ctx, &[], &val, &meta, &mut buf)?;
+            // The changes made to what Data() has loaded are kept, and it sees the new field
+            Self::commit_account_data(ctx, index)?;
+            account.data.borrow_mut()[range].copy_from_slice(&buf);
+            Self::reload_data(ctx, index);
+            Ok(())
+        };
+        Self::unwrap_empty_result(result())
+    }
+
+    fn ffi_old_data_accounts(ctx: &FfiCtx) -> RuntimeResult<GosValue> {
         let inst = Self::get_instruction(ctx);
-        Self::make_pub_key_ptr(ctx, *inst.accounts[index].owner)
+        let indices: Vec<usize> = (0..inst.accounts.len())
+            .filter(|i| Self::old_data_version(inst, *i).is_some())
+            .collect();
+        let array = ctx.new_primitive_array(indices, ValueType::Uint);
+        SliceObj::<ElemWord>::with_array(array, 0, -1)
+            .map(|s| GosValue::new_slice(s, ValueType::Uint))
     }
 
-    fn ffi_account_executable(ctx: &FfiCtx, index: usize) -> bool {
+    fn ffi_account_old_data(ctx: &FfiCtx, index: usize) -> RuntimeResult<GosValue> {
         let inst = Self::get_instruction(ctx);
-        inst.accounts[index].executable
+        let old = Self::old_data_version(inst, index)
+            .ok_or_else(|| RuntimeError::new("account_old_data: not an old version".to_owned()))?;
+        let val = Self::decode_data(ctx, index, &old.discriminator, &old.meta)
+            .map_err(|e| RuntimeError::new(e.to_string()))?;
+        Ok(ctx.new_interface(
+            FfiCtx::new_pointer(val),
+            Some((&inst.tx_meta.migrator_meta, old.meta.ptr_to())),
+        ))
+    }
+
+    fn ffi_account_set_migrated_data(ctx: &FfiCtx, index: usize, data: GosValue) -> GosValue {
+        let result = || -> anyhow::Result<()> {
+            let inst = Self::get_instruction(ctx);
+            inst.account(index)?;
+            let data_meta = inst.ix_meta.accounts[index]
+                .data_meta
+                .as_ref()
+                .ok_or(error!(GolError::DataMetaNotFound))?;
+            let data_ptr = match data.as_non_nil_interface()? {
+                InterfaceObj::Gos(ptr, Some((meta, _)))
+                    if meta.key == data_meta.meta.key && meta.ptr_depth == 1 =>
+                {
+                    ptr.clone()
+                }
+                _ => return Err(error!(GolError::RtCheckMigrateType).into()),
+            };
+            inst.set_data_field(ctx, index, data_ptr)?;
+            // Loaded by Data() from now on, and saved by commit_data if the account is mut
+            inst.data_cache.borrow_mut()[index] = Some(data);
+            Ok(())
+        };
+        Self::unwrap_empty_result(result())
+    }
+
+    fn ffi_account_data_bytes(ctx: &FfiCtx, index: usize) -> RuntimeResult<GosValue> {
+        let account = Self::get_account(ctx, index)?;
+        let bytes = account.data.borrow().to_vec();
+        Self::make_byte_slice(ctx, bytes)
     }
 
-    fn ffi_account_rent_epoch(ctx: &FfiCtx, index: usize) -> u64 {
+    fn ffi_account_read_bytes(
+        ctx: &FfiCtx,
+        index: usize,
+        offset: u64,
+        len: u64,
+    ) -> (GosValue, GosValue) {
+        let result: anyhow::Result<GosValue> = (move || {
+            let account = Self::get_instruction(ctx).account(index)?;
+            let data = account.data.borrow();
+            let range = Self::data_range(data.len(), offset, len)?;
+            Ok(Self::make_byte_slice(ctx, data[range].to_vec())?)
+        })();
+        Self::unwrap_result(result)
+    }
+
+    fn ffi_account_write_bytes(
+        ctx: &FfiCtx,
+        index: usize,
+        offset: u64,
+        bytes: GosValue,
+    ) -> GosValue {
+        let result: anyhow::Result<()> = (move || {
+            let inst = Self::get_instruction(ctx);
+            let account = inst.account(index)?;
+            if *account.owner != crate::ID {
+                return Err(error!(GolError::RtCheckDataOwner).into());
+            }
+            if !account.is_writable {
+                return Err(error!(GolError::RtCheckMutable).into());
+            }
+            let bytes = Self::get_bytes(&bytes)?;
+            // The changes made to what Data() has loaded are kept, and it sees the new bytes
+            Self::commit_account_data(ctx, index)?;
+            {
+                let mut data = account.data.borrow_mut();
+                let range = Self::data_range(data.len(), offset, bytes.len() as u64)?;
+                data[range].copy_from_slice(&bytes);
+            }
+            Self::reload_data(ctx, index);
+            Ok(())
+        })();
+        Self::unwrap_empty_result(result)
+    }
+
+    fn ffi_program_main(ctx: &FfiCtx) -> GosValue {
         let inst = Self::get_instruction(ctx);
-        inst.accounts[index].rent_epoch
+        inst.program_main
+            .borrow()
+            .clone()
+            .unwrap_or(FfiCtx::new_nil(ValueType::Closure))
+    }
+
+    fn ffi_commit_data(ctx: &FfiCtx) -> bool {
+        Self::get_instruction(ctx).keep_error(Self::commit_data(ctx))
     }
 
-    fn ffi_account_data(ctx: &FfiCtx, index: usize) -> GosValue {
+    fn ffi_account_resize(
+        ctx: &FfiCtx,
+        index: usize,
+        payer_index: usize,
+        new_size: u64,
+        signer_seeds: GosValue,
+    ) -> GosValue {
         let inst = Self::get_instruction(ctx);
-        let account = &inst.accounts[index];
-        let account_meta = &inst.ix_meta.accounts[index];
-        let result = || -> anyhow::Result<GosValue> {
-            if let Some(data_meta) = &account_meta.data_meta {
-                let mut buf: &[u8] = &account.data.borrow();
-                GosValue::deserialize_wo_type(data_meta, &ctx.vm_objs.metas, &mut buf)
-                    .map(|val| {
-                        ctx.new_empty_interface(FfiCtx::new_pointer(val), data_meta.ptr_to())
-                    })
-                    .map_err(Into::into)
-            } else {
-                Err(error!(GolError::DataMetaNotFound)).map_err(Into::into)
+        let result: anyhow::Result<()> = (move || {
+            let account = inst.account(index)?.clone();
+            let payer = inst.account(payer_index)?.clone();
+            if *account.owner != crate::ID {
+                return Err(error!(GolError::RtCheckDataOwner).into());
             }
-        }();
-        result.unwrap()
+            if !account.is_writable {
+                return Err(error!(GolError::RtCheckMutable).into());
+            }
+            let size = new_size as usize;
+            let minimum_balance = anchor_lang::prelude::Rent::get()?.minimum_balance(size);
+            let lamports = account.lamports();
+            if minimum_balance > lamports {
+                let ix = solana_program::system_instruction::transfer(
+                    payer.key,
+                    account.key,
+                    minimum_balance - lamports,
+                );
+                Self::invoke_signed(ctx, &ix, &[payer, account.clone()], signer_seeds)?;
+            } else if lamports > minimum_balance && size < account.data_len() {
+                // Shrinking, the lamports no longer needed for rent go back to the payer
+                if !payer.is_writable {
+                    return Err(error!(GolError::RtCheckMutable).into());
+                }
+                let payer_lamports = payer
+                    .lamports()
+                    .checked_add(lamports - minimum_balance)
+                    .ok_or(error!(GolError::RtCheckLamportsOverflow))?;
+                **account.lamports.borrow_mut() = minimum_balance;
+                **payer.lamports.borrow_mut() = payer_lamports;
+                // The cached data may no longer fit, Data() loads it again
+                inst.data_cache.borrow_mut()[index] = None;
+            }
+            // Zero the bytes when growing, so data left from an earlier shrink in the same
+            // transaction doesn't show up again
+            account.realloc(size, true).map_err(Into::into)
+        })();
+        Self::unwrap_empty_result(result)
     }
 
-    fn ffi_account_save_data(ctx: &FfiCtx, index: usize, data_iface: GosValue) {
+    fn ffi_space_of(ctx: &FfiCtx, data: GosValue) -> RuntimeResult<u64> {
         let inst = Self::get_instruction(ctx);
-        let account = &inst.accounts[index];
-        let account_meta = &inst.ix_meta.accounts[index];
-        let result = || -> anyhow::Result<()> {
-            if let Some(_) = &account_meta.data_meta {
-                let mut buf: &mut [u8] = &mut account.data.borrow_mut();
-                let data_ptr = data_iface
-                    .as_non_nil_interface()?
-                    .underlying_value()
-                    .unwrap();
-                let data_obj = ctx.deref_pointer(&data_ptr).unwrap();
-                GosValue::serialize_wo_type(&data_obj, &mut buf).map_err(Into::into)
-            } else {
-                Err(error!(GolError::DataMetaNotFound)).map_err(Into::into)
-            }
-        }();
-        result.unwrap();
+        let meta = match data.as_non_nil_interface()? {
+            InterfaceObj::Gos(_, Some((meta, _))) if meta.ptr_depth <= 1 => meta.key,
+            _ => return Err("SpaceOf: bad data type".to_owned().into()),
+        };
+        inst.tx_meta
+            .instructions
+            .iter()
+            .flat_map(|ix| ix.accounts.iter())
+            .filter_map(|acc| acc.data_meta.as_ref())
+            .find(|x| x.meta.key == meta)
+            .map(|x| x.space)
+            .ok_or("SpaceOf: not an account data type".to_owned().into())
     }
 
+    /// Calls another program. The cached data of the mutable accounts is written to them
+    /// first, so the callee sees it, and the data of the accounts passed to the callee is
+    /// reloaded afterwards, as the callee may have changed it.
     pub(crate) fn invoke_signed(
+        ctx: &FfiCtx,
         instruction: &solana_program::instruction::Instruction,
         account_infos: &[AccountInfo],
         signer_seeds: GosValue,
-        program_id: &Pubkey,
     ) -> anyhow::Result<()> {
-        if !signer_seeds.is_nil() {
-            let buf = Self::get_signers_seed_buf(&signer_seeds, program_id);
-            let mut s = &buf[..];
-            let mut groups: Vec<[&[u8]; 2]> = vec![];
-            let hb = solana_program::hash::HASH_BYTES;
-            while s.len() > 0 {
-                groups.push([&s[0..hb], &s[hb..hb + 1]]);
-                s = &s[hb + 1..];
+        Self::commit_data(ctx)?;
+        let program_id = Self::get_instruction(ctx).gos_program_id;
+        let seeds = Self::get_signer_seeds(&signer_seeds, program_id);
+        let groups: Vec<[&[u8]; 2]> = seeds
+            .iter()
+            .map(|(hash, bump)| [&hash[..], &bump[..]])
+            .collect();
+        let refs = groups.iter().map(|x| &x[..]).collect::<Vec<&[&[u8]]>>();
+        solana_program::program::invoke_signed(instruction, account_infos, &refs)?;
+        let inst = Self::get_instruction(ctx);
+        for (index, account) in inst.accounts.iter().enumerate() {
+            if account_infos.iter().any(|x| x.key == account.key) {
+                Self::reload_data(ctx, index);
             }
-            let refs = groups.iter().map(|x| &x[..]).collect::<Vec<&[&[u8]]>>();
-            solana_program::program::invoke_signed(instruction, account_infos, &refs[..])
-        } else {
-            solana_program::program::invoke_signed(instruction, account_infos, &vec![])
         }
-        .map_err(Into::into)
+        Ok(())
     }
 
     #[inline]
@@ -165,6 +732,40 @@ impl SolanaFfi {
         FfiCtx::new_pointer(pk)
     }
 
+    pub(crate) fn make_byte_slice(ctx: &FfiCtx, bytes: Vec<u8>) -> RuntimeResult<GosValue> {
+        let array = ctx.new_primitive_array(bytes, ValueType::Uint8);
+        SliceObj::<Elem8>::with_array(array, 0, -1)
+            .map(|s| GosValue::new_slice(s, ValueType::Uint8))
+    }
+
+    /// Converts an instruction loaded from the Instructions sysvar to a *TxInstruction
+    fn make_tx_instruction(
+        ctx: &FfiCtx,
+        ix: solana_program::instruction::Instruction,
+    ) -> anyhow::Result<GosValue> {
+        let metas = ix
+            .accounts
+            .iter()
+            .map(|meta| {
+                ctx.new_struct(vec![
+                    ctx.new_primitive_array(meta.pubkey.to_bytes().to_vec(), ValueType::Uint8),
+                    GosValue::from(meta.is_signer),
+                    GosValue::from(meta.is_writable),
+                ])
+            })
+            .collect();
+        let metas = SliceObj::<GosElem>::with_array(ctx.new_array(metas, ValueType::Struct), 0, -1)
+            .map(|s| GosValue::new_slice(s, ValueType::Struct))
+            .map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        let data =
+            Self::make_byte_slice(ctx, ix.data).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+        Ok(FfiCtx::new_pointer(ctx.new_struct(vec![
+            ctx.new_primitive_array(ix.program_id.to_bytes().to_vec(), ValueType::Uint8),
+            metas,
+            data,
+        ])))
+    }
+
     #[inline]
     pub(crate) fn make_pub_key_nilable_ptr(ctx: &FfiCtx, key: COption<Pubkey>) -> GosValue {
         match key {
@@ -173,6 +774,140 @@ impl SolanaFfi {
         }
     }
 
+    /// Deserialize the data of the account, wrapped in an interface. The result is cached
+    /// so that the same object is returned for every call in the instruction.
+    pub(crate) fn load_account_data(ctx: &FfiCtx, index: usize) -> anyhow::Result<GosValue> {
+        let inst = Self::get_instruction(ctx);
+        if let Some(val) = &inst.data_cache.borrow()[index] {
+            return Ok(val.clone());
+        }
+        let (val, meta) = Self::decode_account_data(ctx, index)?;
+        let val = ctx.new_empty_interface(FfiCtx::new_pointer(val), meta.ptr_to());
+        inst.data_cache.borrow_mut()[index] = Some(val.clone());
+        Ok(val)
+    }
+
+    /// Decode the data of the account again into the cached object, after the account has
+    /// been written by other means, so that the values the program holds see the new data.
+    /// The object is dropped from the cache if the account no longer holds the data, or if
+    /// the data type isn't a struct, which can't be updated in place.
+    pub(crate) fn reload_data(ctx: &FfiCtx, index: usize) {
+        let inst = Self::get_instruction(ctx);
+        let cached = match inst.data_cache.borrow().get(index) {
+            Some(Some(val)) => val.clone(),
+            _ => return,
+        };
+        let reload = || -> anyhow::Result<()> {
+            let (val, _) = Self::decode_account_data(ctx, index)?;
+            let ptr = cached.as_non_nil_interface()?.underlying_value().unwrap();
+            let obj = ctx.deref_pointer(ptr)?;
+            if obj.typ() != ValueType::Struct || val.typ() != ValueType::Struct {
+                return Err(anyhow::anyhow!("not a struct"));
+            }
+            let fields = std::mem::take(&mut *val.as_struct().0.borrow_fields_mut());
+            *obj.as_struct().0.borrow_fields_mut() = fields;
+            Ok(())
+        };
+        if reload().is_err() {
+            inst.data_cache.borrow_mut()[index] = None;
+        }
+    }
+
+    /// The data of the account and its type, after checking the owner and the discriminator
+    fn decode_account_data(ctx: &FfiCtx, index: usize) -> anyhow::Result<(GosValue, Meta)> {
+        let inst = Self::get_instruction(ctx);
+        inst.account(index)?;
+        let data_meta = inst.ix_meta.accounts[index]
+            .data_meta
+            .as_ref()
+            .ok_or(error!(GolError::DataMetaNotFound))?;
+        let val = Self::decode_data(ctx, index, &data_meta.discriminator, &data_meta.meta)?;
+        Ok((val, data_meta.meta))
+    }
+
+    /// Decode the data of the account as the type, if the account is owned by the loader
+    /// and the data starts with the discriminator
+    fn decode_data(
+        ctx: &FfiCtx,
+        index: usize,
+        discriminator: &[u8; codec::DISCRIMINATOR_LEN],
+        meta: &Meta,
+    ) -> anyhow::Result<GosValue> {
+        let inst = Self::get_instruction(ctx);
+        let account = inst.account(index)?;
+        // Another program could have written anything, discriminator included
+        if *account.owner != crate::ID {
+            return Err(error!(GolError::RtCheckDataNotOwned).into());
+        }
+        let data = account.data.borrow();
+        if data.len() < codec::DISCRIMINATOR_LEN
+            || data[..codec::DISCRIMINATOR_LEN] != discriminator[..]
+        {
+            return Err(error!(GolError::RtCheckDiscriminator).into());
+        }
+        let mut buf: &[u8] = &data[codec::DISCRIMINATOR_LEN..];
+        Ok(codec::deserialize(
+            ctx,
+            &inst.tx_meta.enums,
+            meta,
+            &mut buf,
+        )?)
+    }
+
+    /// Write the data loaded by Data() back to the mutable accounts
+    pub(crate) fn commit_data(ctx: &FfiCtx) -> anyhow::Result<()> {
+        let inst = Self::get_instruction(ctx);
+        for index in 0..inst.accounts.len() {
+            Self::commit_account_data(ctx, index)?;
+        }
+        Ok(())
+    }
+
+    /// Write the data loaded by Data() back to the account, if it's mutable
+    fn commit_account_data(ctx: &FfiCtx, index: usize) -> anyhow::Result<()> {
+        let inst = Self::get_instruction(ctx);
+        let cached = inst.data_cache.borrow().get(index).cloned().flatten();
+        match cached {
+            Some(data_iface) if inst.ix_meta.accounts[index].is_mut => {
+                let data_ptr = data_iface
+                    .as_non_nil_interface()?
+                    .underlying_value()
+                    .unwrap();
+                let data_obj = ctx.deref_pointer(data_ptr)?;
+                Self::write_account_data(ctx, index, &data_obj)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Serialize the data and write it to the account, together with the discriminator
+    pub(crate) fn write_account_data(
+        ctx: &FfiCtx,
+        index: usize,
+        data_obj: &GosValue,
+    ) -> anyhow::Result<()> {
+        let inst = Self::get_instruction(ctx);
+        let account = inst.account(index)?;
+        let data_meta = inst.ix_meta.accounts[index]
+            .data_meta
+            .as_ref()
+            .ok_or(error!(GolError::DataMetaNotFound))?;
+        let mut buf = data_meta.discriminator.to_vec();
+        codec::serialize(
+            ctx,
+            &inst.tx_meta.enums,
+            data_obj,
+            &data_meta.meta,
+            &mut buf,
+        )?;
+        let mut data = account.data.borrow_mut();
+        if buf.len() > data.len() {
+            return Err(error!(GolError::AccountDataTooSmall).into());
+        }
+        data[..buf.len()].copy_from_slice(&buf);
+        Ok(())
+    }
+
     #[inline]
     pub(crate) fn unwrap_result(result: anyhow::Result<GosValue>) -> (GosValue, GosValue) {
         match result {
@@ -192,12 +927,54 @@ impl SolanaFfi {
         }
     }
 
+    /// The account referred by the handle, a bad handle makes the VM panic
+    pub(crate) fn get_account<'a, 'info>(
+        ctx: &'a FfiCtx,
+        index: usize,
+    ) -> RuntimeResult<&'a AccountInfo<'info>> {
+        Self::get_instruction(ctx)
+            .account(index)
+            .map_err(|e| RuntimeError::new(e.to_string()))
+    }
+
     pub(crate) fn get_instruction<'a, 'info>(ctx: &'a FfiCtx) -> &'a Instruction<'a, 'info> {
         let ud = ctx.user_data.unwrap();
         let p = ud as *const Instruction;
         unsafe { p.as_ref() }.unwrap()
     }
 
+    /// Converts the Go `[]AccountMeta` into the metas of a CPI and the accounts it needs
+    pub(crate) fn get_account_metas<'info>(
+        ctx: &FfiCtx,
+        metas: &GosValue,
+    ) -> anyhow::Result<(Vec<AccountMeta>, Vec<AccountInfo<'info>>)> {
+        let inst = Self::get_instruction(ctx);
+        let mut account_metas = vec![];
+        let mut account_infos = vec![];
+        if let Some((slice, _)) = metas.as_gos_slice() {
+            for x in slice.as_rust_slice().iter() {
+                let struct_ref = x.borrow();
+                let fields = struct_ref.as_struct().0.borrow_fields();
+                let account = inst.account(*fields[0].as_uint())?;
+                let (is_signer, is_writable) = (*fields[1].as_bool(), *fields[2].as_bool());
+                account_metas.push(match is_writable {
+                    true => AccountMeta::new(*account.key, is_signer),
+                    false => AccountMeta::new_readonly(*account.key, is_signer),
+                });
+                account_infos.push(account.clone());
+            }
+        }
+        Ok((account_metas, account_infos))
+    }
+
+    /// The content of a Go `[]byte`, a nil slice is empty
+    pub(crate) fn get_bytes(val: &GosValue) -> RuntimeResult<Vec<u8>> {
+        match val.is_nil() {
+            true => Ok(vec![]),
+            false => Ok(FfiCtx::slice_as_primitive_slice::<u8, u8>(val)?.to_vec()),
+        }
+    }
+
     pub(crate) fn get_pub_key(ctx: &FfiCtx, ptr: &GosValue) -> RuntimeResult<Pubkey> {
         let ptr_obj = ptr.as_non_nil_pointer()?;
         let pk = ptr_obj.deref(&ctx.stack, &ctx.vm_objs.packages)?;
@@ -205,23 +982,129 @@ impl SolanaFfi {
         Ok(Pubkey::from(<[u8; 32]>::try_from(slice).unwrap()))
     }
 
-    pub(crate) fn get_signers_seed_buf(seeds: &GosValue, program_id: &Pubkey) -> Vec<u8> {
-        if let Some((slice, _)) = seeds.as_gos_slice() {
-            let data = slice.as_rust_slice();
-            data.iter().fold(vec![], |mut acc, x| {
-                let struct_ref = x.borrow();
-                let fields = struct_ref.as_struct().0.borrow_fields();
-                assert!(fields.len() == 2);
-                let seed: &[u8] = &fields[0].as_string().as_raw_slice();
-                let mut hashed: Vec<u8> = Self::get_seed_hash(seed, program_id).try_into().unwrap();
-                let bump = fields[1].as_uint8();
-                acc.append(&mut hashed);
-                acc.push(*bump);
-                acc
-            })
-        } else {
-            vec![]
+    /// The seeds of the PDAs signing a CPI, the hashed seed and the bump of every SeedBump.
+    /// A nil slice has none.
+    pub(crate) fn get_signer_seeds(
+        seeds: &GosValue,
+        program_id: &Pubkey,
+    ) -> Vec<([u8; solana_program::hash::HASH_BYTES], [u8; 1])> {
+        match seeds.as_gos_slice() {
+            Some((slice, _)) => slice
+                .as_rust_slice()
+                .iter()
+                .map(|x| {
+                    let struct_ref = x.borrow();
+                    let fields = struct_ref.as_struct().0.borrow_fields();
+                    let seed: &[u8] = &fields[0].as_string().as_raw_slice();
+                    (
+                        Self::get_seed_hash(seed, program_id),
+                        [*fields[1].as_uint8()],
+                    )
+                })
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// The old version of the data type the account holds, if any. Only the accounts owned
+    /// by the loader are migrated, the data of any other can't be trusted.
+    pub(crate) fn old_data_version<'a>(
+        inst: &'a Instruction,
+        index: usize,
+    ) -> Option<&'a OldDataMeta> {
+        let data_meta = inst.ix_meta.accounts.get(index)?.data_meta.as_ref()?;
+        let account = inst.accounts.get(index)?;
+        if *account.owner != crate::ID {
+            return None;
+        }
+        let data = account.data.borrow();
+        let disc = data.get(..codec::DISCRIMINATOR_LEN)?;
+        data_meta
+            .old_versions
+            .iter()
+            .find(|x| x.discriminator[..] == *disc)
+    }
+
+    /// The range in the account data and the type of the field at the path,
+    /// for an account declared with `data:"T,zerocopy"`
+    fn locate_field(
+        ctx: &FfiCtx,
+        index: usize,
+        path: &str,
+    ) -> anyhow::Result<(std::ops::Range<usize>, Meta)> {
+        let inst = Self::get_instruction(ctx);
+        let account = inst.account(index)?;
+        if *account.owner != crate::ID {
+            return Err(error!(GolError::RtCheckDataNotOwned).into());
+        }
+        let data_meta = inst.ix_meta.accounts[index]
+            .data_meta
+            .as_ref()
+            .filter(|x| x.zero_copy)
+            .ok_or(error!(GolError::RtCheckNotZeroCopy))?;
+        let metas = &ctx.vm_objs.metas;
+        let (offset, meta) =
+            codec::field_location(metas, &data_meta.meta, &data_meta.field_offsets, path)
+                .ok_or(error!(GolError::RtCheckFieldPath))?;
+        let size = codec::fixed_size(metas, &meta).ok_or(error!(GolError::RtCheckFieldPath))?;
+        let data = account.data.borrow();
+        if data.len() < codec::DISCRIMINATOR_LEN
+            || data[..codec::DISCRIMINATOR_LEN] != data_meta.discriminator
+        {
+            return Err(error!(GolError::RtCheckDiscriminator).into());
         }
+        Ok((
+            Self::data_range(
+                data.len(),
+                (codec::DISCRIMINATOR_LEN + offset) as u64,
+                size as u64,
+            )?,
+            meta,
+        ))
+    }
+
+    fn data_range(
+        data_len: usize,
+        offset: u64,
+        len: u64,
+    ) -> anyhow::Result<std::ops::Range<usize>> {
+        let end = offset
+            .checked_add(len)
+            .filter(|end| *end <= data_len as u64)
+            .ok_or(error!(GolError::RtCheckDataOutOfRange))?;
+        Ok(offset as usize..end as usize)
+    }
+
+    /// Creates a rent exempt account owned by `owner` with the system program
+    fn create_account(
+        ctx: &FfiCtx,
+        from_index: usize,
+        to_index: usize,
+        space: u64,
+        owner: &Pubkey,
+        signer_seeds: GosValue,
+    ) -> anyhow::Result<()> {
+        let inst = Self::get_instruction(ctx);
+        let from = inst.account(from_index)?.clone();
+        let to = inst.account(to_index)?.clone();
+        let lamports = Rent::get()?.minimum_balance(space as usize);
+        let ix = solana_program::system_instruction::create_account(
+            from.key, to.key, lamports, space, owner,
+        );
+        Self::invoke_signed(ctx, &ix, &[from, to], signer_seeds)
+    }
+
+    /// The PDA of the seed and bump, without searching for the bump like find_program_address
+    pub(crate) fn create_program_address(
+        seed: &[u8],
+        bump: u8,
+        program_id: &Pubkey,
+    ) -> anyhow::Result<Pubkey> {
+        let hashed = Self::get_seed_hash(seed, program_id);
+        Ok(Pubkey::create_program_address(
+            &[&hashed[..], &[bump]],
+            &crate::ID,
+        )?)
     }
 
     pub(crate) fn get_seed_hash(
@@ -18,10 +18,8 @@ pub struct TokenFfi;
 impl TokenFfi {
     fn ffi_unpack_mint(ctx: &FfiCtx, account_index: usize) -> (GosValue, GosValue) {
         let inst = SolanaFfi::get_instruction(ctx);
-        let account = &inst.accounts[account_index];
-        let mint = Mint::unpack(&account.data.borrow());
         let result: anyhow::Result<GosValue> = (move || {
-            let mint = mint?;
+            let mint = Mint::unpack(&inst.account(account_index)?.data.borrow())?;
             let mint_authority = SolanaFfi::make_pub_key_nilable_ptr(ctx, mint.mint_authority);
             let supply = GosValue::from(mint.supply);
             let decimals = GosValue::from(mint.decimals);
@@ -41,10 +39,9 @@ impl TokenFfi {
 
     fn ffi_unpack_account(ctx: &FfiCtx, account_index: usize) -> (GosValue, GosValue) {
         let inst = SolanaFfi::get_instruction(ctx);
-        let account = &inst.accounts[account_index];
-        let account = spl_token::state::Account::unpack(&account.data.borrow());
         let result: anyhow::Result<GosValue> = (move || {
-            let account = account?;
+            let account = inst.account(account_index)?;
+            let account = spl_token::state::Account::unpack(&account.data.borrow())?;
             let mint = SolanaFfi::make_pub_key_ptr(ctx, account.mint);
             let owner = SolanaFfi::make_pub_key_ptr(ctx, account.owner);
             let amount = GosValue::from(account.amount);
@@ -81,12 +78,11 @@ impl TokenFfi {
         signer_seeds: GosValue,
     ) -> GosValue {
         let inst = SolanaFfi::get_instruction(ctx);
-        let from = inst.accounts[from_index].clone();
-        let to = inst.accounts[to_index].clone();
-        let mint = inst.accounts[mint_index].clone();
-        let wallet =
-            SolanaFfi::get_pub_key(ctx, &wallet).expect("ffi_create_and_init_account: bad wallet");
         let result: anyhow::Result<()> = (move || {
+            let wallet = SolanaFfi::get_pub_key(ctx, &wallet)?;
+            let from = inst.account(from_index)?.clone();
+            let to = inst.account(to_index)?.clone();
+            let mint = inst.account(mint_index)?.clone();
             let len = spl_token::state::Account::LEN;
             let space = len as u64;
             let sol_rent = Rent::get()?;
@@ -98,12 +94,7 @@ impl TokenFfi {
                 space,
                 &spl_token::ID,
             );
-            SolanaFfi::invoke_signed(
-                &ix,
-                &[from, to.clone()],
-                signer_seeds.clone(),
-                inst.gos_program_id,
-            )?;
+            SolanaFfi::invoke_signed(ctx, &ix, &[from, to.clone()], signer_seeds.clone())?;
 
             let ix = spl_token::instruction::initialize_account3(
                 &spl_token::ID,
@@ -111,7 +102,7 @@ impl TokenFfi {
                 mint.key,
                 &wallet,
             )?;
-            SolanaFfi::invoke_signed(&ix, &[to, mint], signer_seeds, inst.gos_program_id)
+            SolanaFfi::invoke_signed(ctx, &ix, &[to, mint], signer_seeds)
         })();
         SolanaFfi::unwrap_empty_result(result)
     }
@@ -125,9 +116,9 @@ impl TokenFfi {
     ) -> GosValue {
         let result: anyhow::Result<()> = (move || {
             let inst = SolanaFfi::get_instruction(ctx);
-            let account = &inst.accounts[account_index];
-            let dest = &inst.accounts[dest_index];
-            let wallet = &inst.accounts[wallet_index];
+            let account = inst.account(account_index)?;
+            let dest = inst.account(dest_index)?;
+            let wallet = inst.account(wallet_index)?;
             let ix = spl_token::instruction::close_account(
                 &spl_token::ID,
                 account.key,
@@ -136,10 +127,10 @@ impl TokenFfi {
                 &[], // TODO: support multisig
             )?;
             SolanaFfi::invoke_signed(
+                ctx,
                 &ix,
                 &[account.clone(), dest.clone(), wallet.clone()],
                 signer_seeds,
-                inst.gos_program_id,
             )
         })();
         SolanaFfi::unwrap_empty_result(result)
@@ -155,8 +146,8 @@ impl TokenFfi {
     ) -> GosValue {
         let result: anyhow::Result<()> = (move || {
             let inst = SolanaFfi::get_instruction(ctx);
-            let account_or_mint = &inst.accounts[account_or_mint_index];
-            let current_auth = &inst.accounts[current_auth_index];
+            let account_or_mint = inst.account(account_or_mint_index)?;
+            let current_auth = inst.account(current_auth_index)?;
             let mut spl_new_authority: Option<Pubkey> = None;
             if !new_auth_key.is_nil() {
                 spl_new_authority = Some(SolanaFfi::get_pub_key(ctx, &new_auth_key)?);
@@ -171,10 +162,10 @@ impl TokenFfi {
                 &[], // TODO: Support multisig signers.
             )?;
             SolanaFfi::invoke_signed(
+                ctx,
                 &ix,
                 &[account_or_mint.clone(), current_auth.clone()],
                 signer_seeds,
-                inst.gos_program_id,
             )
         })();
         SolanaFfi::unwrap_empty_result(result)
@@ -190,9 +181,9 @@ impl TokenFfi {
     ) -> GosValue {
         let result: anyhow::Result<()> = (move || {
             let inst = SolanaFfi::get_instruction(ctx);
-            let from = &inst.accounts[from_index];
-            let to = &inst.accounts[to_index];
-            let auth = &inst.accounts[auth_index];
+            let from = inst.account(from_index)?;
+            let to = inst.account(to_index)?;
+            let auth = inst.account(auth_index)?;
             let ix = spl_token::instruction::transfer(
                 &spl_token::ID,
                 from.key,
@@ -202,10 +193,10 @@ impl TokenFfi {
                 amount,
             )?;
             SolanaFfi::invoke_signed(
+                ctx,
                 &ix,
                 &[from.clone(), to.clone(), auth.clone()],
                 signer_seeds,
-                inst.gos_program_id,
             )
         })();
         SolanaFfi::unwrap_empty_result(result)
@@ -221,9 +212,9 @@ impl TokenFfi {
     ) -> GosValue {
         let result: anyhow::Result<()> = (move || {
             let inst = SolanaFfi::get_instruction(ctx);
-            let mint = &inst.accounts[mint_index];
-            let dest = &inst.accounts[dest_index];
-            let auth = &inst.accounts[auth_index];
+            let mint = inst.account(mint_index)?;
+            let dest = inst.account(dest_index)?;
+            let auth = inst.account(auth_index)?;
             let ix = spl_token::instruction::mint_to(
                 &spl_token::ID,
                 mint.key,
@@ -233,10 +224,10 @@ impl TokenFfi {
                 amount,
             )?;
             SolanaFfi::invoke_signed(
+                ctx,
                 &ix,
                 &[mint.clone(), dest.clone(), auth.clone()],
                 signer_seeds,
-                inst.gos_program_id,
             )
         })();
         SolanaFfi::unwrap_empty_result(result)
@@ -252,9 +243,9 @@ impl TokenFfi {
     ) -> GosValue {
         let result: anyhow::Result<()> = (move || {
             let inst = SolanaFfi::get_instruction(ctx);
-            let account = &inst.accounts[account_index];
-            let mint = &inst.accounts[mint_index];
-            let auth = &inst.accounts[auth_index];
+            let account = inst.account(account_index)?;
+            let mint = inst.account(mint_index)?;
+            let auth = inst.account(auth_index)?;
             let ix = spl_token::instruction::burn(
                 &spl_token::ID,
                 account.key,
@@ -264,10 +255,10 @@ impl TokenFfi {
                 amount,
             )?;
             SolanaFfi::invoke_signed(
+                ctx,
                 &ix,
                 &[account.clone(), mint.clone(), auth.clone()],
                 signer_seeds,
-                inst.gos_program_id,
             )
         })();
         SolanaFfi::unwrap_empty_result(result)
@@ -286,10 +277,10 @@ impl TokenFfi {
     ) -> GosValue {
         let result: anyhow::Result<()> = (move || {
             let inst = SolanaFfi::get_instruction(ctx);
-            let mint = &inst.accounts[mint_index];
-            let wallet = &inst.accounts[wallet_index];
-            let payer = &inst.accounts[payer_index];
-            let dest = &inst.accounts[dest_index];
+            let mint = inst.account(mint_index)?;
+            let wallet = inst.account(wallet_index)?;
+            let payer = inst.account(payer_index)?;
+            let dest = inst.account(dest_index)?;
             let ix = if idempotent {
                 create_associated_token_account_idempotent(
                     payer.key,
@@ -301,17 +292,17 @@ impl TokenFfi {
                 create_associated_token_account(payer.key, wallet.key, mint.key, &spl_token::ID)
             };
             SolanaFfi::invoke_signed(
+                ctx,
                 &ix,
                 &[
                     payer.clone(),
                     dest.clone(),
                     wallet.clone(),
                     mint.clone(),
-                    inst.accounts[sys_index].clone(),
-                    inst.accounts[tp_index].clone(),
+                    inst.account(sys_index)?.clone(),
+                    inst.account(tp_index)?.clone(),
                 ],
                 signer_seeds,
-                inst.gos_program_id,
             )
         })();
         SolanaFfi::unwrap_empty_result(result)
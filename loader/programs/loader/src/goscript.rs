@@ -23,7 +23,18 @@ pub fn run(
     solana::SolanaFfi::register(&mut ffi);
     token::TokenFfi::register(&mut ffi);
 
-    let panic_info = go_vm::run(&bc, &ffi);
+    // solana.runMain takes the place of main() for the run, it calls main() and then does
+    // the work left to the runtime inside the VM
+    let program_main = swap_main(bc);
+    *ix.program_main.borrow_mut() = program_main.as_ref().map(|(_, main)| main.clone());
+    let panic_info = go_vm::run(bc, &ffi);
+    if let Some((index, main)) = program_main {
+        *bc.objects.packages[bc.main_pkg].member_mut(index) = main;
+    }
+
+    if let Some(e) = ix.error.borrow_mut().take() {
+        return Err(e);
+    }
     if let Some(pi) = panic_info {
         let call_stack = go_vm::CallStackDisplay::new(&pi, bc);
         msg!("GolanaVM panic: {}\n", pi.msg);
@@ -33,13 +44,39 @@ pub fn run(
     Ok(())
 }
 
+/// Puts solana.runMain in the place of the main() of the program, returning the index and
+/// the value of main() to be put back after the run
+fn swap_main(bc: &Bytecode) -> Option<(types::OpIndex, GosValue)> {
+    let packages = &bc.objects.packages;
+    let solana = packages.iter().find(|pkg| pkg.name() == "solana")?;
+    let run_main = solana.member(*solana.member_index("runMain")?).clone();
+    let main_pkg = &packages[bc.main_pkg];
+    let index = *main_pkg.member_index("main")?;
+    let main = std::mem::replace(&mut *main_pkg.member_mut(index), run_main);
+    Some((index, main))
+}
+
+/// The anchor error in an error from the FFI code, other errors become InvalidAccountData
+fn anchor_error(e: anyhow::Error) -> Error {
+    e.downcast::<Error>()
+        .unwrap_or_else(|_| ProgramError::InvalidAccountData.into())
+}
+
 pub(crate) struct Instruction<'a, 'info> {
     pub gos_program_id: &'a Pubkey,
     pub accounts: &'a [AccountInfo<'info>],
     pub args: &'a Vec<u8>,
     pub iface_meta: &'a types::Meta,
+    pub tx_meta: &'a TxMeta,
     pub ix_meta: &'a IxMeta,
-    pub gos_ix: RefCell<Option<GosValue>>,
+    // Set by prepare_ix before Validate and main() run
+    pub gos_ix: RefCell<GosValue>,
+    // The data loaded from the accounts, indexed by account
+    pub data_cache: RefCell<Vec<Option<GosValue>>>,
+    // The main() of the program, called by solana.runMain
+    pub program_main: RefCell<Option<GosValue>>,
+    // The error the instruction fails with once the VM returns
+    pub error: RefCell<Option<Error>>,
 }
 
 impl<'a, 'info> Instruction<'a, 'info>
@@ -68,23 +105,84 @@ where
             accounts,
             args,
             iface_meta,
+            tx_meta,
             ix_meta,
-            gos_ix: RefCell::new(None),
+            gos_ix: RefCell::new(FfiCtx::new_nil(types::ValueType::Interface)),
+            data_cache: RefCell::new(vec![None; accounts.len()]),
+            program_main: RefCell::new(None),
+            error: RefCell::new(None),
         })
     }
 
-    pub(crate) fn get_ix(&self, ctx: &FfiCtx) -> GosValue {
-        let gos_ix: &mut Option<GosValue> = &mut self.gos_ix.borrow_mut();
-        match gos_ix {
-            Some(val) => val.clone(),
-            None => {
-                let ix = self.deserialize_ix(ctx).unwrap();
-                *gos_ix = Some(ix.clone());
-                ix
+    /// Keeps the error of the result, if any, for the instruction to fail with, returning
+    /// whether it's Ok
+    pub(crate) fn keep_error(&self, result: anyhow::Result<()>) -> bool {
+        match result {
+            Ok(_) => true,
+            Err(e) => {
+                self.error.borrow_mut().get_or_insert(anchor_error(e));
+                false
             }
         }
     }
 
+    /// Checks the accounts and decodes the arguments into the Go Ix, before anything else
+    /// runs, so that they fail the instruction with their own errors
+    pub(crate) fn prepare_ix(&self, ctx: &FfiCtx) -> bool {
+        let result = self.deserialize_ix(ctx).map(|ix| {
+            *self.gos_ix.borrow_mut() = ix;
+        });
+        self.keep_error(result.map_err(Into::into))
+    }
+
+    pub(crate) fn get_ix(&self) -> GosValue {
+        self.gos_ix.borrow().clone()
+    }
+
+    /// Points the `<account>_data` field of the Ix, if the account has one, at the data
+    /// migrated from an old version, as the field is left nil until then
+    pub(crate) fn set_data_field(
+        &self,
+        ctx: &FfiCtx,
+        index: usize,
+        data_ptr: GosValue,
+    ) -> types::RuntimeResult<()> {
+        if let Some(pos) = self.ix_meta.data_fields.iter().position(|x| *x == index) {
+            let gos_ix = self.gos_ix.borrow();
+            let ix_ptr = gos_ix
+                .as_non_nil_interface()?
+                .underlying_value()
+                .ok_or_else(|| types::RuntimeError::new("set_data_field: bad Ix".to_owned()))?;
+            let ix = ctx.deref_pointer(ix_ptr)?;
+            ix.as_struct().0.borrow_fields_mut()[self.accounts.len() + pos] = data_ptr;
+        }
+        Ok(())
+    }
+
+    /// The account referred by a Go `Account` handle, which is an index into the accounts
+    pub(crate) fn account(&self, index: usize) -> Result<&'a AccountInfo<'info>> {
+        self.accounts
+            .get(index)
+            .ok_or(error!(GolError::RtCheckBadAccountHandle))
+    }
+
+    /// Returns the current instruction as a `Validator`, or nil if it has no Validate method
+    pub(crate) fn get_validator(&self, ctx: &FfiCtx) -> types::RuntimeResult<GosValue> {
+        if self.ix_meta.validate_method.is_none() {
+            return Ok(FfiCtx::new_nil(types::ValueType::Interface));
+        }
+        let ix = self.get_ix();
+        let ptr = ix
+            .as_non_nil_interface()?
+            .underlying_value()
+            .ok_or_else(|| types::RuntimeError::new("get_validator: bad Ix".to_owned()))?
+            .clone();
+        Ok(ctx.new_interface(
+            ptr,
+            Some((&self.tx_meta.validator_meta, self.ix_meta.gos_meta.ptr_to())),
+        ))
+    }
+
     fn deserialize_ix(&self, ctx: &FfiCtx) -> Result<GosValue> {
         let mut fields = vec![];
         for (i, acc_meta) in self.ix_meta.accounts.iter().enumerate() {
@@ -97,15 +195,33 @@ where
             }
             fields.push(i.into());
         }
+        self.check_duplicates()?;
+
+        for index in self.ix_meta.data_fields.iter() {
+            // Old versions are only loaded when runMain migrates them, see set_data_field
+            if solana::SolanaFfi::old_data_version(self, *index).is_some() {
+                fields.push(FfiCtx::new_nil(types::ValueType::Pointer));
+                continue;
+            }
+            let data = solana::SolanaFfi::load_account_data(ctx, *index).map_err(anchor_error)?;
+            fields.push(
+                data.as_interface()
+                    .unwrap()
+                    .underlying_value()
+                    .unwrap()
+                    .clone(),
+            );
+        }
 
         let mut buf: &[u8] = &self.args;
         for arg_meta in self.ix_meta.args.iter() {
-            // todo: type checks
-            fields.push(GosValue::deserialize_wo_type(
-                &arg_meta.1,
-                &ctx.vm_objs.metas,
-                &mut buf,
-            )?);
+            fields.push(
+                codec::deserialize(ctx, &self.tx_meta.enums, &arg_meta.1, &mut buf)
+                    .map_err(Self::args_error)?,
+            );
+        }
+        if !buf.is_empty() {
+            return Err(error!(GolError::RtCheckArgsTrailingBytes));
         }
 
         let ix = ctx.new_struct(fields);
@@ -114,4 +230,370 @@ where
             Some((self.iface_meta, self.ix_meta.gos_meta.ptr_to())),
         ))
     }
+
+    /// The same account can't be passed for two fields if one of them is `mut`, unless one
+    /// of them allows it with `dup=<field>`. It goes by the declaration rather than by
+    /// is_writable, which is set for both as soon as the transaction has the account writable.
+    fn check_duplicates(&self) -> Result<()> {
+        let metas = &self.ix_meta.accounts;
+        for (i, a) in self.accounts.iter().enumerate() {
+            for (j, b) in self.accounts.iter().enumerate().skip(i + 1) {
+                if a.key == b.key
+                    && (metas[i].is_mut || metas[j].is_mut)
+                    && metas[i].dup != Some(j)
+                    && metas[j].dup != Some(i)
+                {
+                    return Err(error!(GolError::RtCheckDuplicateAccount));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn args_error(e: std::io::Error) -> Error {
+        match codec::DecodeError::of(&e) {
+            codec::DecodeError::Truncated => error!(GolError::RtCheckArgsTruncated),
+            codec::DecodeError::InvalidBool => error!(GolError::RtCheckArgsInvalidBool),
+            codec::DecodeError::LengthOverflow => error!(GolError::RtCheckArgsLengthOverflow),
+            codec::DecodeError::InvalidValue => error!(GolError::RtCheckArgsInvalidValue),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// Compiles and checks a program made of the declarations and the body of main
+    fn build(decls: &str, main: &str) -> (Bytecode, TxMeta) {
+        build_src(format!(
+            "package main\n\nimport . \"solana\"\n\n{}\n\nfunc main() {{\n{}\n}}\n",
+            decls, main
+        ))
+    }
+
+    fn build_src(src: String) -> (Bytecode, TxMeta) {
+        let (reader, path) = go_engine::SourceReader::fs_lib_and_string(
+            PathBuf::from("../../../cli/go"),
+            src.into(),
+        );
+        let bc = go_engine::Engine::new()
+            .compile(&reader, &path, true, false, false)
+            .unwrap_or_else(|e| panic!("compile error: {}", e));
+        let meta = golana::check(&bc).unwrap();
+        (bc, meta)
+    }
+
+    /// The state of an account, for making an AccountInfo
+    struct TestAccount {
+        key: Pubkey,
+        owner: Pubkey,
+        lamports: u64,
+        data: Vec<u8>,
+        is_signer: bool,
+        is_writable: bool,
+    }
+
+    impl TestAccount {
+        fn new(is_signer: bool, is_writable: bool) -> TestAccount {
+            TestAccount {
+                key: Pubkey::new_unique(),
+                owner: crate::ID,
+                lamports: 1_000_000,
+                data: vec![],
+                is_signer,
+                is_writable,
+            }
+        }
+
+        fn info(&mut self) -> AccountInfo<'_> {
+            AccountInfo::new(
+                &self.key,
+                self.is_signer,
+                self.is_writable,
+                &mut self.lamports,
+                &mut self.data,
+                &self.owner,
+                false,
+                0,
+            )
+        }
+    }
+
+    /// The id of the programs run by the tests
+    const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+    /// Runs the instruction, the error is the error code, or None for a panic in Go
+    fn run_ix(
+        bc: &Bytecode,
+        meta: &TxMeta,
+        id: &str,
+        accounts: &mut [TestAccount],
+        args: Vec<u8>,
+    ) -> std::result::Result<(), Option<u32>> {
+        let infos: Vec<AccountInfo> = accounts.iter_mut().map(|x| x.info()).collect();
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            run(&PROGRAM_ID, bc, meta, &infos, id, args).map_err(|e| match e {
+                Error::AnchorError(e) => Some(e.error_code_number),
+                Error::ProgramError(e) => panic!("unexpected error: {}", e),
+            })
+        }))
+        .unwrap_or(Err(None))
+    }
+
+    #[test]
+    fn validate_runs_without_get_ix() {
+        let (bc, meta) = build(
+            r#"
+type ZeroAmount struct{}
+
+func (e ZeroAmount) Error() string { return "zero amount" }
+
+type IxPay struct {
+	payer  Account `account:"signer"`
+	amount uint64
+}
+
+func (ix *IxPay) Validate() error {
+	if ix.amount == 0 {
+		return ZeroAmount{}
+	}
+	return nil
+}
+
+func (ix *IxPay) Process() {}"#,
+            "",
+        );
+        let mut accounts = [TestAccount::new(true, false)];
+        let amount = |x: u64| x.to_le_bytes().to_vec();
+        assert_eq!(
+            run_ix(&bc, &meta, "IxPay", &mut accounts, amount(1)),
+            Ok(())
+        );
+        // main() doesn't call GetIx, Validate runs anyway
+        assert_eq!(
+            run_ix(&bc, &meta, "IxPay", &mut accounts, amount(0)),
+            Err(None)
+        );
+    }
+
+    #[test]
+    fn malformed_args() {
+        let (bc, meta) = build(
+            r#"
+type IxPay struct {
+	payer  Account `account:"signer"`
+	amount uint64
+	memo   string
+}
+
+func (ix *IxPay) Process() {}"#,
+            "Execute()",
+        );
+        let mut accounts = [TestAccount::new(true, false)];
+        let mut args = vec![];
+        (5u64, "go").serialize(&mut args).unwrap();
+        assert_eq!(
+            run_ix(&bc, &meta, "IxPay", &mut accounts, args.clone()),
+            Ok(())
+        );
+
+        let code = |e: GolError| Err(Some(e.into()));
+        assert_eq!(
+            run_ix(&bc, &meta, "IxPay", &mut accounts, args[..9].to_vec()),
+            code(GolError::RtCheckArgsTruncated)
+        );
+        let mut trailing = args.clone();
+        trailing.push(0);
+        assert_eq!(
+            run_ix(&bc, &meta, "IxPay", &mut accounts, trailing),
+            code(GolError::RtCheckArgsTrailingBytes)
+        );
+        let mut not_signer = [TestAccount::new(false, false)];
+        assert_eq!(
+            run_ix(&bc, &meta, "IxPay", &mut not_signer, args),
+            code(GolError::RtCheckSigner)
+        );
+    }
+
+    #[test]
+    fn malformed_key() {
+        // A nil key is an error for the program, rather than a panic in the loader, so it
+        // can be recovered from
+        let (bc, meta) = build_src(
+            r#"package main
+
+import (
+	. "solana"
+	"token"
+)
+
+type IxKey struct {
+	payer Account `account:"signer, mut"`
+	to    Account `account:"mut"`
+	mint  Account
+}
+
+func (ix *IxKey) Process() {
+	err := token.CreateAndInitAccount(ix.payer, ix.to, ix.mint, nil, nil)
+	Assert(err != nil, "CreateAndInitAccount didn't fail")
+
+	failed := false
+	func() {
+		defer func() { failed = recover() != nil }()
+		FindProgramAddress("vault", nil)
+	}()
+	Assert(failed, "FindProgramAddress didn't fail")
+}
+
+func main() {
+	Execute()
+}
+"#
+            .to_owned(),
+        );
+        let mut accounts = [
+            TestAccount::new(true, true),
+            TestAccount::new(false, true),
+            TestAccount::new(false, false),
+        ];
+        assert_eq!(run_ix(&bc, &meta, "IxKey", &mut accounts, vec![]), Ok(()));
+    }
+
+    #[test]
+    fn pda_matches_client() {
+        let (bc, meta) = build(
+            r#"
+type IxPda struct {
+	owner      Account
+	plain      PublicKey
+	plainBump  uint8
+	single     PublicKey
+	singleBump uint8
+	multi      PublicKey
+	multiBump  uint8
+}
+
+func (ix *IxPda) Process() {
+	addr, bump := FindProgramAddress("vault", GetId())
+	Assert(*addr == ix.plain && bump == ix.plainBump, "plain seed")
+	addr, bump = FindProgramAddress(Seeds("vault"), GetId())
+	Assert(*addr == ix.single && bump == ix.singleBump, "single part")
+	addr, bump = FindProgramAddress(Seeds("position", ix.owner.Key(), uint64(1)), GetId())
+	Assert(*addr == ix.multi && bump == ix.multiBump, "multiple parts")
+}"#,
+            "Execute()",
+        );
+        let mut accounts = [TestAccount::new(false, false)];
+        // What findAddr(seed) and seeds(...parts) of the TS client compute
+        let find_addr = |seed: &[u8]| {
+            let hashed = solana_program::hash::hash(&[PROGRAM_ID.as_ref(), seed].concat());
+            Pubkey::find_program_address(&[hashed.as_ref()], &crate::ID)
+        };
+        let seeds = |parts: &[&[u8]]| -> Vec<u8> {
+            parts
+                .iter()
+                .flat_map(|x| [&(x.len() as u32).to_le_bytes()[..], x].concat())
+                .collect()
+        };
+        let mut args = vec![];
+        find_addr(b"vault").serialize(&mut args).unwrap();
+        find_addr(&seeds(&[b"vault"])).serialize(&mut args).unwrap();
+        let owner = accounts[0].key.to_bytes();
+        find_addr(&seeds(&[b"position", &owner, &1u64.to_le_bytes()]))
+            .serialize(&mut args)
+            .unwrap();
+        assert_eq!(run_ix(&bc, &meta, "IxPda", &mut accounts, args), Ok(()));
+    }
+
+    #[test]
+    fn zero_copy_foreign_owner() {
+        let (bc, meta) = build(
+            r#"
+type Book struct {
+	count uint32
+}
+
+type IxRead struct {
+	book  Account `data:"Book,zerocopy"`
+	owned bool
+}
+
+func (ix *IxRead) Process() {
+	count, err := ix.book.ReadField("count")
+	if ix.owned {
+		AbortOnError(err)
+		Assert(count.(uint32) == 3, "count")
+	} else {
+		Assert(err != nil, "the data of another program was read")
+	}
+}"#,
+            "Execute()",
+        );
+        let data_meta = meta.instructions[0].accounts[0].data_meta.as_ref().unwrap();
+        let mut book = TestAccount::new(false, false);
+        book.data = [&data_meta.discriminator[..], &3u32.to_le_bytes()].concat();
+        let mut accounts = [book];
+        assert_eq!(run_ix(&bc, &meta, "IxRead", &mut accounts, vec![1]), Ok(()));
+        // The same data in an account of another program is not read
+        accounts[0].owner = Pubkey::new_unique();
+        assert_eq!(run_ix(&bc, &meta, "IxRead", &mut accounts, vec![0]), Ok(()));
+    }
+
+    #[test]
+    fn migrate_old_version() {
+        let (bc, meta) = build(
+            r#"
+type CounterV0 struct {
+	count uint32
+}
+
+func (old *CounterV0) Migrate() interface{} {
+	return &Counter{count: uint64(old.count)}
+}
+
+type Counter struct {
+	count uint64
+}
+
+type IxBump struct {
+	counter      Account `account:"mut" data:"Counter,version=1"`
+	counter_data *Counter
+}
+
+func (ix *IxBump) Process() {
+	Assert(ix.counter_data.count == 3, "counter_data")
+	data, err := ix.counter.DataAs(&Counter{})
+	AbortOnError(err)
+	Assert(data.(*Counter).count == 3, "DataAs")
+	ix.counter_data.count += 1
+}"#,
+            "Execute()",
+        );
+        let old = [
+            &codec::account_discriminator("Counter")[..],
+            &3u32.to_le_bytes(),
+            &[0; 4],
+        ]
+        .concat();
+        let mut counter = TestAccount::new(false, true);
+        counter.data = old.clone();
+        let mut accounts = [counter];
+        assert_eq!(run_ix(&bc, &meta, "IxBump", &mut accounts, vec![]), Ok(()));
+        let current = [
+            &codec::account_discriminator("CounterV1")[..],
+            &4u64.to_le_bytes(),
+        ]
+        .concat();
+        assert_eq!(accounts[0].data, current);
+
+        // The old data of an account of another program is left alone
+        accounts[0].data = old;
+        accounts[0].owner = Pubkey::new_unique();
+        assert_eq!(
+            run_ix(&bc, &meta, "IxBump", &mut accounts, vec![]),
+            Err(Some(GolError::RtCheckDataNotOwned.into()))
+        );
+    }
 }
@@ -180,6 +180,7 @@ pub mod loader {
                 let meta = golana::check(&bc)?;
                 mem_dump.bc_ptr = obj_to_ptr(bc);
                 mem_dump.meta_ptr = obj_to_ptr(meta);
+                mem_dump.meta_version = golana::META_VERSION;
             }
         }
         mem_dump.data_offset = bc_data.len() - bytes.len();
@@ -193,6 +194,10 @@ pub mod loader {
     pub fn gol_execute(ctx: Context<GolExecute>, id: String, args: Vec<u8>) -> Result<()> {
         msg!(&id);
         let mem_dump = &mut ctx.accounts.mem_dump.load()?;
+        require!(
+            mem_dump.meta_version == golana::META_VERSION,
+            GolError::MetaVersionMismatch
+        );
         restore_memory(mem_dump)?;
 
         DualMalloc::set_use_smalloc(true);
@@ -265,6 +270,7 @@ fn initialize_impl(
     mem_dump.bytecode = bc_pk;
     mem_dump.meta_ptr = 0;
     mem_dump.bc_ptr = 0;
+    mem_dump.meta_version = 0;
 
     Ok(())
 }
@@ -341,7 +347,8 @@ pub struct GolMemoryDump {
     pub bc_ptr: usize,
     // Below are temporary fields for deserializing the bytecode.
     pub finished_steps: u32,
-    _padding: u32,
+    // The golana::META_VERSION of the TxMeta, 0 for the programs finalized before it's set
+    pub meta_version: u32,
     pub data_offset: usize,
     pub meta_objs_ptr: usize,
     pub func_objs_ptr: usize,
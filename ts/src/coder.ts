@@ -0,0 +1,46 @@
+import { BorshAccountsCoder, Idl as AnchorIdl } from "@project-serum/anchor";
+import { Idl } from "./idl.js";
+import { IdlAccounts } from "./types.js";
+
+const STORED_AS = "stored as: ";
+
+/**
+ * Decodes the data accounts of a Golana program, i.e. the types referenced by
+ * `data:"..."` tags, which are stored as an 8-byte discriminator followed by
+ * the borsh encoded data, the same layout as Anchor accounts.
+ * The discriminator comes from the name the data is stored under, which for
+ * versioned types is in the docs of the account, e.g. `stored as: CounterV1`.
+ */
+export class AccountsCoder<IDL extends Idl = Idl> {
+  private _coder: BorshAccountsCoder;
+  private _storedNames: Map<string, string>;
+
+  constructor(idl: IDL) {
+    this._storedNames = new Map(
+      (idl.accounts ?? []).map((acc) => {
+        const doc = acc.docs?.find((x) => x.startsWith(STORED_AS));
+        return [acc.name, doc ? doc.slice(STORED_AS.length) : acc.name];
+      })
+    );
+    const accounts = (idl.accounts ?? []).map((acc) => ({
+      ...acc,
+      name: this.storedName(acc.name),
+    }));
+    this._coder = new BorshAccountsCoder({ ...idl, accounts } as unknown as AnchorIdl);
+  }
+
+  public decode<N extends keyof IdlAccounts<IDL> & string>(
+    accountName: N,
+    data: Buffer
+  ): IdlAccounts<IDL>[N] {
+    return this._coder.decode(this.storedName(accountName), data);
+  }
+
+  public discriminator(accountName: string): Buffer {
+    return BorshAccountsCoder.accountDiscriminator(this.storedName(accountName));
+  }
+
+  private storedName(accountName: string): string {
+    return this._storedNames.get(accountName) ?? accountName;
+  }
+}
@@ -114,8 +114,8 @@ export type IdlType =
   | "bytes"
   | "string"
   | "publicKey"
-  // | IdlTypeDefined
-  // | IdlTypeOption
+  | IdlTypeDefined
+  | IdlTypeOption
   // | IdlTypeCOption
   | IdlTypeVec
   | IdlTypeArray;
@@ -4,14 +4,17 @@ import { Program as AnchorProgram, Provider, AnchorProvider, getProvider, utils,
 import * as anchor from "@project-serum/anchor";
 import * as borsh from 'borsh';
 import { IDL as LoaderIDL, Loader } from "./loader.js";
-import { Idl, IdlInstruction, IdlAccountItem, IdlAccounts, isIdlAccounts, IdlType } from "./idl.js";
-import { AllInstructions, MethodsFn, MakeMethodsNamespace, ArgsTuple, IdlTypes } from './types.js';
+import { Idl, IdlInstruction, IdlAccountItem, IdlAccounts, isIdlAccounts, IdlType, IdlTypeDef, IdlField } from "./idl.js";
+import { AllInstructions, MethodsFn, MakeMethodsNamespace, ArgsTuple, IdlTypes, IdlAccounts as IdlAccountsData } from './types.js';
+import { AccountsCoder } from "./coder.js";
 import { createHash } from "crypto";
 
 let LOADER_ID = "---Not initialized!!!!---";
 
 // re-export AnchorProvider
 export { AnchorProvider };
+export { AccountsCoder };
+export type { IdlAccountsData as IdlAccounts };
 
 export interface AnchorWallet {
   publicKey: PublicKey;
@@ -55,6 +58,10 @@ type PartialAccount<A extends IdlAccountItem> = A extends IdlAccounts
   ? Address | null
   : Address;
 
+export type AccountNamespace<IDL extends Idl = Idl> = {
+  [N in keyof IdlAccountsData<IDL> & string]: AccountClient<IDL, N>;
+};
+
 export class Program<IDL extends Idl = Idl> {
 
   private _bytecodePK: PublicKey;
@@ -67,6 +74,11 @@ export class Program<IDL extends Idl = Idl> {
    */
   readonly methods: MethodsNamespace<IDL>;
 
+  /**
+   * The namespace provides handles to fetch and decode the data accounts of the program.
+   */
+  readonly account: AccountNamespace<IDL>;
+
   /**
    * Address of the Golana loader.
    */
@@ -93,6 +105,14 @@ export class Program<IDL extends Idl = Idl> {
     return this._bytecodePK;
   }
 
+  /**
+   * The memory dump account of the program, it identifies the program when it's
+   * called by another Golana program.
+   */
+  public get memDumpPK(): PublicKey {
+    return this._memDumpPK;
+  }
+
   private constructor(
     private _idl: IDL,
     bytecodePKAndMemDumpPK: [PublicKey, PublicKey],
@@ -109,10 +129,19 @@ export class Program<IDL extends Idl = Idl> {
         MethodsBuilderFactory.build<IDL, typeof idlIx>(
           this._golanaLoader,
           this._memDumpPK,
-          idlIx
+          idlIx,
+          _idl.types ?? []
         )
       ])
     ) as unknown as MethodsNamespace<IDL>;
+
+    const coder = new AccountsCoder<IDL>(_idl);
+    this.account = Object.fromEntries(
+      (_idl.accounts ?? []).map(idlAcc => [
+        idlAcc.name,
+        new AccountClient(coder, idlAcc.name, _provider)
+      ])
+    ) as unknown as AccountNamespace<IDL>;
   }
 
   static async create<IDL extends Idl>(
@@ -130,27 +159,92 @@ export class Program<IDL extends Idl = Idl> {
     return [await PublicKey.createWithSeed(pk, "BC" + name, addr), await PublicKey.createWithSeed(pk, "MM" + name, addr)];
   }
 
-  async findAddr(seed: string) {
+  /**
+   * Finds the PDA of a seed, the same as `FindProgramAddress(seed, GetId())` on chain.
+   * For a seed built with `Seeds(...)`, pass `seeds(...)` with the same parts.
+   */
+  async findAddr(seed: SeedPart) {
     let buf: Buffer | Uint8Array;
 
-    const fullSeed = Buffer.concat([this._bytecodePK.toBuffer(), Buffer.from(utils.bytes.utf8.encode(seed))]);
+    const fullSeed = Buffer.concat([this._bytecodePK.toBuffer(), seedPartBytes(seed)]);
     buf = createHash("sha256").update(fullSeed).digest();
     
     return PublicKey.findProgramAddressSync([buf], this._golanaLoader.programId);
   }
 }
 
+export type SeedPart = string | Buffer | Uint8Array | PublicKey;
+
+/**
+ * Encodes the parts of a seed the same way as `Seeds(...)` does on chain, each part is
+ * prefixed with its length as a little-endian u32.
+ * Integer parts have to be passed as little-endian bytes, e.g. `new BN(n).toArrayLike(Buffer, "le", 8)`.
+ */
+export function seeds(...parts: SeedPart[]): Buffer {
+  return Buffer.concat(parts.map((part) => {
+    const bytes = seedPartBytes(part);
+    const len = Buffer.alloc(4);
+    len.writeUInt32LE(bytes.length);
+    return Buffer.concat([len, bytes]);
+  }));
+}
+
+function seedPartBytes(part: SeedPart): Buffer {
+  if (typeof part === "string") {
+    return Buffer.from(utils.bytes.utf8.encode(part));
+  } else if (part instanceof PublicKey) {
+    return part.toBuffer();
+  }
+  return Buffer.from(part);
+}
+
+export class AccountClient<IDL extends Idl, N extends keyof IdlAccountsData<IDL> & string> {
+  constructor(
+    private _coder: AccountsCoder<IDL>,
+    private _name: N,
+    private _provider: Provider,
+  ) {}
+
+  /**
+   * Returns the decoded account data, or null if the account doesn't exist.
+   */
+  async fetchNullable(address: Address): Promise<IdlAccountsData<IDL>[N] | null> {
+    const info = await this._provider.connection.getAccountInfo(address2Pubkey(address));
+    if (info === null) {
+      return null;
+    }
+    return this.decode(info.data);
+  }
+
+  /**
+   * Returns the decoded account data, throws if the account doesn't exist.
+   */
+  async fetch(address: Address): Promise<IdlAccountsData<IDL>[N]> {
+    const data = await this.fetchNullable(address);
+    if (data === null) {
+      throw new Error(`account ${address.toString()} not found`);
+    }
+    return data;
+  }
+
+  public decode(data: Buffer): IdlAccountsData<IDL>[N] {
+    return this._coder.decode(this._name, data);
+  }
+}
+
 export class MethodsBuilderFactory {
   public static build<IDL extends Idl, I extends AllInstructions<IDL>>(
     loader: AnchorProgram<Loader>,
     memDumpPK: PublicKey,
-    idlIx: AllInstructions<IDL>
+    idlIx: AllInstructions<IDL>,
+    idlTypes: IdlTypeDef[]
   ): MethodsFn<IDL, I, MethodsBuilder<IDL, I>> {
     return (...args) =>
       new MethodsBuilder(
         loader,
         memDumpPK,
         idlIx,
+        idlTypes,
         args
       );
   }
@@ -163,6 +257,7 @@ export class MethodsBuilder<IDL extends Idl, I extends AllInstructions<IDL>> {
     loader: AnchorProgram<Loader>,
     private _memDumpPK: PublicKey,
     private _idlIx: IdlInstruction,
+    private _idlTypes: IdlTypeDef[],
     args: ArgsTuple<I["args"], IdlTypes<IDL>>,
   ) {
     const exec = loader.methods.golExecute(_idlIx.name, this._argsBuffer(args));
@@ -177,11 +272,8 @@ export class MethodsBuilder<IDL extends Idl, I extends AllInstructions<IDL>> {
     const buffers:Array<Uint8Array> = [];
     args.forEach((arg, i) => {
       const type = this._idlIx.args[i].type;
-      const schema = getTypeSchema(type);
-      if (type === "publicKey") {
-        arg = (arg as PublicKey).toBytes();
-      }
-      buffers.push(borsh.serialize(schema, arg));
+      const schema = getTypeSchema(type, this._idlTypes);
+      buffers.push(borsh.serialize(schema, toBorshValue(type, arg, this._idlTypes)));
     });
     return Buffer.concat(buffers);
   }
@@ -292,7 +384,7 @@ export function address2Pubkey(address: Address): PublicKey {
   return address instanceof PublicKey ? address : new PublicKey(address);
 }
 
-function getTypeSchema(idlType: IdlType): borsh.Schema {
+function getTypeSchema(idlType: IdlType, types: IdlTypeDef[]): borsh.Schema {
   if (typeof idlType === 'string') {
     if ([
       "bool",
@@ -316,11 +408,68 @@ function getTypeSchema(idlType: IdlType): borsh.Schema {
     } else {
       throw new Error(`Not a valid type: ${idlType}`);
     }
+  } else if ("option" in idlType) {
+    return {option: getTypeSchema(idlType.option, types)};
   } else if ("vec" in idlType) {
-    return {array:{type: idlType.vec as string}};
+    return {array:{type: getTypeSchema(idlType.vec, types)}};
   } else if ("array" in idlType) {
-    return {array:{type: idlType.array[0] as string, len: idlType.array[1]}};
+    return {array:{type: getTypeSchema(idlType.array[0], types), len: idlType.array[1]}};
+  } else if ("defined" in idlType) {
+    const typeDef = getTypeDef(idlType.defined, types);
+    if (typeDef.type.kind === "struct") {
+      return {struct: fieldsSchema(typeDef.type.fields, types)};
+    }
+    // Enums are encoded as { variantName: { ...fields } }
+    return {enum: typeDef.type.variants.map((v) => ({
+      struct: {[v.name]: {struct: fieldsSchema((v.fields ?? []) as IdlField[], types)}}
+    }))};
   } else {
     throw new Error(`Not a valid type: ${idlType}`);
   }
 }
+
+function fieldsSchema(fields: IdlField[], types: IdlTypeDef[]): Record<string, borsh.Schema> {
+  return Object.fromEntries(fields.map((f) => [f.name, getTypeSchema(f.type, types)]));
+}
+
+function getTypeDef(name: string, types: IdlTypeDef[]): IdlTypeDef {
+  const typeDef = types.find((t) => t.name === name);
+  if (typeDef === undefined) {
+    throw new Error(`Type not found: ${name}`);
+  }
+  return typeDef;
+}
+
+// Convert the PublicKeys in a value to bytes, which is what borsh expects.
+function toBorshValue(idlType: IdlType, value: any, types: IdlTypeDef[]): any {
+  if (value === null || value === undefined) {
+    return value;
+  } else if (idlType === "publicKey") {
+    return (value as PublicKey).toBytes();
+  } else if (typeof idlType === 'string') {
+    return value;
+  } else if ("option" in idlType) {
+    return toBorshValue(idlType.option, value, types);
+  } else if ("vec" in idlType) {
+    return (value as any[]).map((v) => toBorshValue(idlType.vec, v, types));
+  } else if ("array" in idlType) {
+    return (value as any[]).map((v) => toBorshValue(idlType.array[0], v, types));
+  } else if ("defined" in idlType) {
+    const typeDef = getTypeDef(idlType.defined, types);
+    if (typeDef.type.kind === "struct") {
+      return fieldsValue(typeDef.type.fields, value, types);
+    }
+    const variant = typeDef.type.variants.find((v) => v.name in value);
+    if (variant === undefined) {
+      throw new Error(`Not a valid variant of ${idlType.defined}`);
+    }
+    const fields = (variant.fields ?? []) as IdlField[];
+    return {[variant.name]: fieldsValue(fields, value[variant.name] ?? {}, types)};
+  } else {
+    return value;
+  }
+}
+
+function fieldsValue(fields: IdlField[], value: any, types: IdlTypeDef[]): any {
+  return Object.fromEntries(fields.map((f) => [f.name, toBorshValue(f.type, value[f.name], types)]));
+}